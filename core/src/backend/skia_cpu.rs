@@ -1,8 +1,10 @@
-use skia::{Paint, Path, PathBuilder, Color, FillRule, ClipMask, Stroke, PixmapPaint, Transform};
+use skia::{Paint, Path, PathBuilder, Color, FillRule, ClipMask, Stroke, Rect, Transform, GradientStop, SpreadMode, Point, Pattern, FilterQuality};
 use crate::backend::PainterBackend;
 use crate::f32x2;
-use crate::operate::Segment;
-use crate::paint::shader::Shader;
+use crate::image::ImageData;
+use crate::operate::{PixelSegment, Segment};
+use crate::paint::blend::BlendMode;
+use crate::paint::shader::{interpolate_stops, Repetition, Shader};
 use crate::path::{PathData, PathSegment};
 use crate::skia::Pixmap;
 
@@ -46,71 +48,86 @@ impl SkiaCPURender {
         self.pixmap.save_png(path).ok()
     }
 
-    pub fn fill_path(&mut self, path: &PathData, paint: &crate::paint::Paint, fill_rule: &crate::paint::FillRule, clip_mask: Option<crate::paint::ClipMask>, clear: Option<crate::operate::ClearRect>) -> Option<()> {
-        let paint = SkiaCPURender::build_paint(paint, self.anti_alias, self.force_hq_pipeline);
+    pub fn fill_path(&mut self, path: &PathData, paint: &crate::paint::Paint, fill_rule: &crate::paint::FillRule, image_smoothing: bool, clip_mask: Option<crate::paint::ClipMask>, clear: Option<crate::operate::ClearRect>) -> Option<()> {
+        let quality = if image_smoothing { FilterQuality::Bilinear } else { FilterQuality::Nearest };
+        let area = self.raster_area(path, 0.0);
+        let raster = area.and_then(|area| SkiaCPURender::rasterize_shader(&paint.shader, area, quality));
+        let paint = SkiaCPURender::build_paint(paint, self.anti_alias, self.force_hq_pipeline, raster.as_ref(), quality);
         let path = SkiaCPURender::build_path(path)?;
         let fill_rule = SkiaCPURender::build_fill_rule(fill_rule);
-        let mut clip = ClipMask::new();
-        let result = {
-            let has_clip = SkiaCPURender::build_clip_mask(&mut clip, clip_mask, self.pixmap.width(), self.pixmap.height()).is_some();
-            let mut has_clear = false;
-            if let Some(clear) = clear {
-                let clear_path = clear.get_path();
-                if let Some(path) = SkiaCPURender::build_path(&clear_path) {
-                    has_clear = true;
-                    if clip.is_empty() {
-                        clip.set_path(self.pixmap.width(), self.pixmap.height(), &path, FillRule::default(), self.anti_alias)
-                    } else {
-                        clip.intersect_path(&path, FillRule::default(), self.anti_alias)
-                    };
-                }
-            }
-            has_clear || has_clip
-        };
-        let clip_mask = if result { Some(&clip) } else { None };
-        self.pixmap.fill_path(&path, &paint, fill_rule, Default::default(), clip_mask)
+        let clip = self.build_clip(clip_mask, clear);
+        self.pixmap.fill_path(&path, &paint, fill_rule, Default::default(), clip.as_ref())
     }
 
-    pub fn stroke_path(&mut self, path: &PathData, stroke: &crate::paint::stroke::Stroke, clip_mask: Option<crate::paint::ClipMask>, clear: Option<crate::operate::ClearRect>) -> Option<()> {
-        let (paint, stroke) = SkiaCPURender::build_stroke(stroke, self.anti_alias, self.force_hq_pipeline);
+    pub fn stroke_path(&mut self, path: &PathData, stroke: &crate::paint::stroke::Stroke, image_smoothing: bool, clip_mask: Option<crate::paint::ClipMask>, clear: Option<crate::operate::ClearRect>) -> Option<()> {
+        let quality = if image_smoothing { FilterQuality::Bilinear } else { FilterQuality::Nearest };
+        // miter joins reach the furthest out of the path
+        let area = self.raster_area(path, stroke.width * stroke.miter_limit.max(1.0) / 2.0);
+        let raster = area.and_then(|area| SkiaCPURender::rasterize_shader(&stroke.paint.shader, area, quality));
+        let (paint, stroke) = SkiaCPURender::build_stroke(stroke, self.anti_alias, self.force_hq_pipeline, raster.as_ref(), quality);
         let path = SkiaCPURender::build_path(path)?;
-        let mut clip = ClipMask::new();
-        let result = {
-            let has_clip = SkiaCPURender::build_clip_mask(&mut clip, clip_mask, self.pixmap.width(), self.pixmap.height()).is_some();
-            let mut has_clear = false;
-            if let Some(clear) = clear {
-                if let Some(path) = SkiaCPURender::build_path(&clear.get_path()) {
-                    has_clear = true;
-                    if clip.is_empty() {
-                        clip.set_path(self.pixmap.width(), self.pixmap.height(), &path, FillRule::default(), self.anti_alias)
-                    } else {
-                        clip.intersect_path(&path, FillRule::default(), self.anti_alias)
-                    };
-                }
-            }
-            has_clear || has_clip
-        };
-        let clip_mask = if result { Some(&clip) } else { None };
-        self.pixmap.stroke_path(&path, &paint, &stroke, Default::default(), clip_mask)
+        let clip = self.build_clip(clip_mask, clear);
+        self.pixmap.stroke_path(&path, &paint, &stroke, Default::default(), clip.as_ref())
     }
 
-    pub fn draw_pixel(&mut self, pixels: &[u8], size: f32x2, opacity: f32, transform: &crate::transform::Transform, clip_mask: Option<crate::paint::ClipMask>) -> Option<()> {
+    pub fn draw_pixel(&mut self, seg: &PixelSegment) -> Option<()> {
         let pix =
             {
-                let mut pix = Pixmap::new(size.x() as u32, size.y() as u32)?;
+                let mut pix = Pixmap::new(seg.size.x() as u32, seg.size.y() as u32)?;
                 let data = pix.data_mut();
-                data.clone_from_slice(&pixels);
+                data.clone_from_slice(&seg.data);
                 pix
             };
+        let clip = self.build_clip(seg.clip.clone(), seg.clear_rect.clone());
+        // `draw_pixmap` fills the whole pixmap, a fill of the source rect keeps partly used pixels inside it
+        let (x, y, width, height) = seg.src_rect.unwrap_or((0.0, 0.0, seg.size.x(), seg.size.y()));
+        let rect = Rect::from_xywh(x, y, width, height)?;
+        let quality = if seg.image_smoothing { FilterQuality::Bilinear } else { FilterQuality::Nearest };
+        let mut paint = Paint::default();
+        paint.shader = Pattern::new(pix.as_ref(), SpreadMode::Pad, quality, seg.opacity, Transform::identity());
+        paint.blend_mode = SkiaCPURender::build_blend_mode(&seg.blend_mode);
+        paint.anti_alias = false;
+        let transform = SkiaCPURender::build_transform(&seg.transform);
+        self.pixmap.fill_rect(rect, &paint, transform, clip.as_ref())
+    }
+
+    /// Reads the (x, y, width, height) area back as straight alpha pixels.
+    ///
+    /// Pixels outside of the surface are transparent black.
+    pub fn get_image_data(&self, x: i32, y: i32, width: u32, height: u32) -> Option<ImageData> {
+        let mut image_data = ImageData::new(width, height)?;
+        for row in 0..height {
+            for col in 0..width {
+                let (px, py) = (x + col as i32, y + row as i32);
+                if px < 0 || py < 0 {
+                    continue;
+                }
+                if let Some(color) = self.pixmap.pixel(px as u32, py as u32) {
+                    let color = color.demultiply();
+                    let index = ((row * width + col) * 4) as usize;
+                    image_data.data[index..index + 4].copy_from_slice(&[color.red(), color.green(), color.blue(), color.alpha()]);
+                }
+            }
+        }
+        Some(image_data)
+    }
+
+    /// Combines the clip path and the cleared area into one mask, `None` when neither exists.
+    fn build_clip(&self, clip_mask: Option<crate::paint::ClipMask>, clear: Option<crate::operate::ClearRect>) -> Option<ClipMask> {
         let mut clip = ClipMask::new();
-        let result = {
-            SkiaCPURender::build_clip_mask(&mut clip, clip_mask, self.pixmap.width(), self.pixmap.height())
-        };
-        let clip_mask = result.and_then(|_| Some(&clip));
-        let mut paint = PixmapPaint::default();
-        paint.opacity = opacity;
-        let transform = SkiaCPURender::build_transform(transform);
-        self.pixmap.draw_pixmap(0, 0, pix.as_ref(), &paint, transform, clip_mask)
+        let has_clip = SkiaCPURender::build_clip_mask(&mut clip, clip_mask, self.pixmap.width(), self.pixmap.height()).is_some();
+        let mut has_clear = false;
+        if let Some(clear) = clear {
+            if let Some(path) = SkiaCPURender::build_path(&clear.get_path()) {
+                has_clear = true;
+                if clip.is_empty() {
+                    clip.set_path(self.pixmap.width(), self.pixmap.height(), &path, FillRule::default(), self.anti_alias)
+                } else {
+                    clip.intersect_path(&path, FillRule::default(), self.anti_alias)
+                };
+            }
+        }
+        if has_clear || has_clip { Some(clip) } else { None }
     }
 
 
@@ -127,7 +144,83 @@ impl SkiaCPURender {
         pb.finish()
     }
 
-    fn build_paint(paint: &crate::paint::Paint, anti_alias: bool, force_hq_pipeline: bool) -> Paint {
+    /// The device pixels (x, y, width, height) the path covers, grown by `outset` and cut to the canvas.
+    fn raster_area(&self, path: &PathData, outset: f32) -> Option<(u32, u32, u32, u32)> {
+        let bbox = path.get_bounding_box()?;
+        let x1 = (bbox.x1.min(bbox.x2) - outset - 1.0).floor().max(0.0);
+        let y1 = (bbox.y1.min(bbox.y2) - outset - 1.0).floor().max(0.0);
+        let x2 = (bbox.x1.max(bbox.x2) + outset + 1.0).ceil().min(self.pixmap.width() as f32);
+        let y2 = (bbox.y1.max(bbox.y2) + outset + 1.0).ceil().min(self.pixmap.height() as f32);
+        if x2 <= x1 || y2 <= y1 {
+            return None;
+        }
+        Some((x1 as u32, y1 as u32, (x2 - x1) as u32, (y2 - y1) as u32))
+    }
+
+    /// Renders shaders tiny-skia can't express into a pixmap over the device `area`,
+    /// repeating patterns get their tile instead. The transform places the pixmap on the device.
+    /// Patterns are sampled with `quality`.
+    fn rasterize_shader(shader: &Shader, area: (u32, u32, u32, u32), quality: FilterQuality) -> Option<(Pixmap, Transform)> {
+        let (left, top, width, height) = area;
+        let offset = Transform::from_translate(left as f32, top as f32);
+        match shader {
+            Shader::Pattern(pattern) if pattern.repetition == Repetition::Repeat => {
+                let mut pixmap = Pixmap::new(pattern.width(), pattern.height())?;
+                pixmap.data_mut().clone_from_slice(&pattern.data);
+                Some((pixmap, SkiaCPURender::build_transform(&pattern.get_transform())))
+            }
+            Shader::Pattern(pattern) => {
+                let inverse = pattern.get_transform().invert()?;
+                let mut pixmap = Pixmap::new(width, height)?;
+                let data = pixmap.data_mut();
+                for y in 0..height {
+                    for x in 0..width {
+                        let (px, py) = inverse.apply((left + x) as f32 + 0.5, (top + y) as f32 + 0.5);
+                        let pixel = match quality {
+                            FilterQuality::Nearest => pattern.sample(px, py),
+                            _ => pattern.sample_bilinear(px, py),
+                        };
+                        if let Some(pixel) = pixel {
+                            let index = ((y * width + x) * 4) as usize;
+                            data[index..index + 4].copy_from_slice(&pixel);
+                        }
+                    }
+                }
+                Some((pixmap, offset))
+            }
+            Shader::RadialGradient(gradient) if gradient.r0 > 0.0 && !gradient.is_degenerate() => {
+                let pixmap = SkiaCPURender::rasterize_gradient(&gradient.stops, &gradient.transform, area, |x, y| gradient.position(x, y))?;
+                Some((pixmap, offset))
+            }
+            Shader::ConicGradient(gradient) if !gradient.is_degenerate() => {
+                let pixmap = SkiaCPURender::rasterize_gradient(&gradient.stops, &gradient.transform, area, |x, y| Some(gradient.position(x, y)))?;
+                Some((pixmap, offset))
+            }
+            _ => None
+        }
+    }
+
+    fn rasterize_gradient(stops: &[crate::paint::shader::GradientStop], transform: &crate::transform::Transform, area: (u32, u32, u32, u32), position: impl Fn(f32, f32) -> Option<f32>) -> Option<Pixmap> {
+        let (left, top, width, height) = area;
+        let inverse = SkiaCPURender::build_transform(transform).invert()?;
+        let mut pixmap = Pixmap::new(width, height)?;
+        let pixels = pixmap.pixels_mut();
+        for y in 0..height {
+            for x in 0..width {
+                let mut point = [Point::from_xy((left + x) as f32 + 0.5, (top + y) as f32 + 0.5)];
+                inverse.map_points(&mut point);
+                if let Some(t) = position(point[0].x, point[0].y) {
+                    let color = interpolate_stops(stops, t);
+                    let co = Color::from_rgba(color.r.get(), color.g.get(), color.b.get(), color.a.get()).unwrap();
+                    pixels[(y * width + x) as usize] = co.premultiply().to_color_u8();
+                }
+            }
+        }
+        Some(pixmap)
+    }
+
+    /// `quality` samples repeating pattern tiles, the other rasterized shaders are already in device pixels.
+    fn build_paint<'a>(paint: &crate::paint::Paint, anti_alias: bool, force_hq_pipeline: bool, raster: Option<&'a (Pixmap, Transform)>, quality: FilterQuality) -> Paint<'a> {
         let mut pt = Paint::default();
         pt.anti_alias = anti_alias;
         pt.force_hq_pipeline = force_hq_pipeline;
@@ -138,16 +231,70 @@ impl SkiaCPURender {
                 let co = Color::from_rgba(color.r.get(), color.g.get(), color.b.get(), color.a.get()).unwrap();
                 pt.set_color(co);
             }
-            // todo
-            Shader::LinearGradient(_) => {}
-            Shader::RadialGradient(_) => {}
-            Shader::Pattern => {}
+            Shader::LinearGradient(gradient) => {
+                let shader = if gradient.is_degenerate() {
+                    None
+                } else {
+                    skia::LinearGradient::new(
+                        Point::from_xy(gradient.x0, gradient.y0),
+                        Point::from_xy(gradient.x1, gradient.y1),
+                        SkiaCPURender::build_gradient_stops(&gradient.stops),
+                        SpreadMode::Pad,
+                        SkiaCPURender::build_transform(&gradient.transform),
+                    )
+                };
+                match shader {
+                    Some(shader) => pt.shader = shader,
+                    None => pt.set_color(Color::TRANSPARENT),
+                }
+            }
+            Shader::RadialGradient(gradient) => {
+                let shader = if gradient.is_degenerate() {
+                    None
+                } else if let Some((pixmap, transform)) = raster {
+                    Some(Pattern::new(pixmap.as_ref(), SpreadMode::Pad, FilterQuality::Nearest, 1.0, *transform))
+                } else {
+                    skia::RadialGradient::new(
+                        Point::from_xy(gradient.x0, gradient.y0),
+                        Point::from_xy(gradient.x1, gradient.y1),
+                        gradient.r1,
+                        SkiaCPURender::build_gradient_stops(&gradient.stops),
+                        SpreadMode::Pad,
+                        SkiaCPURender::build_transform(&gradient.transform),
+                    )
+                };
+                match shader {
+                    Some(shader) => pt.shader = shader,
+                    None => pt.set_color(Color::TRANSPARENT),
+                }
+            }
+            Shader::ConicGradient(_) => {
+                match raster {
+                    Some((pixmap, transform)) => pt.shader = Pattern::new(pixmap.as_ref(), SpreadMode::Pad, FilterQuality::Nearest, 1.0, *transform),
+                    None => pt.set_color(Color::TRANSPARENT),
+                }
+            }
+            Shader::Pattern(pattern) => {
+                let spread_mode = if pattern.repetition == Repetition::Repeat { SpreadMode::Repeat } else { SpreadMode::Pad };
+                match raster {
+                    Some((pixmap, transform)) => pt.shader = Pattern::new(pixmap.as_ref(), spread_mode, quality, 1.0, *transform),
+                    None => pt.set_color(Color::TRANSPARENT),
+                }
+            }
         }
         pt
     }
 
-    fn build_stroke(stroke: &crate::paint::stroke::Stroke, anti_alias: bool, force_hq_pipeline: bool) -> (Paint, Stroke) {
-        let paint = SkiaCPURender::build_paint(&stroke.paint, anti_alias, force_hq_pipeline);
+    fn build_gradient_stops(stops: &[crate::paint::shader::GradientStop]) -> Vec<GradientStop> {
+        stops.iter().map(|stop| {
+            let color = stop.color;
+            let co = Color::from_rgba(color.r.get(), color.g.get(), color.b.get(), color.a.get()).unwrap();
+            GradientStop::new(stop.offset, co)
+        }).collect()
+    }
+
+    fn build_stroke<'a>(stroke: &crate::paint::stroke::Stroke, anti_alias: bool, force_hq_pipeline: bool, raster: Option<&'a (Pixmap, Transform)>, quality: FilterQuality) -> (Paint<'a>, Stroke) {
+        let paint = SkiaCPURender::build_paint(&stroke.paint, anti_alias, force_hq_pipeline, raster, quality);
         let mut st = Stroke::default();
         st.width = stroke.width;
         st.line_cap = stroke.line_cap;
@@ -172,6 +319,13 @@ impl SkiaCPURender {
         Some(())
     }
 
+    fn build_blend_mode(blend_mode: &BlendMode) -> skia::BlendMode {
+        match blend_mode {
+            BlendMode::SourceOver => skia::BlendMode::SourceOver,
+            BlendMode::Source => skia::BlendMode::Source,
+        }
+    }
+
     fn build_transform(ts: &crate::transform::Transform) -> Transform {
         let mut transform = Transform::default();
         transform.sx = ts.a;
@@ -198,11 +352,11 @@ impl PainterBackend for SkiaCPURender {
     fn draw(&mut self, segment: &Segment) {
         match segment {
             Segment::Pixel(ref seg) => {
-                self.draw_pixel(&seg.data, seg.size, seg.opacity, &seg.transform, seg.clip.clone())
+                self.draw_pixel(seg)
             }
             Segment::Vector(ref seg) => {
-                seg.fill.as_ref().and_then(|paint| self.fill_path(&seg.path, &paint, &seg.fill_rule, seg.clip.clone(), seg.clear_rect.clone()));
-                seg.stroke.as_ref().and_then(|stroke| self.stroke_path(&seg.path, &stroke, seg.clip.clone(), seg.clear_rect.clone()))
+                seg.fill.as_ref().and_then(|paint| self.fill_path(&seg.path, &paint, &seg.fill_rule, seg.image_smoothing, seg.clip.clone(), seg.clear_rect.clone()));
+                seg.stroke.as_ref().and_then(|stroke| self.stroke_path(&seg.path, &stroke, seg.image_smoothing, seg.clip.clone(), seg.clear_rect.clone()))
             }
         };
     }
@@ -14,7 +14,14 @@ macro_rules! into_str {
 pub mod svg_methods {
     use crate::svg::{Document, Node};
     use crate::svg::node::element::{Element, Style};
+    use crate::skia::PixmapRef;
     use crate::paint::color::Color;
+    use crate::paint::shader::{interpolate_stops, GradientStop};
+    use crate::path::PathData;
+    use crate::transform::Transform;
+
+    /// Number of wedges a conic gradient is approximated with.
+    const CONIC_WEDGES: usize = 360;
 
     pub fn create_svg_tag(width: f32, height: f32) -> Document {
         let mut svg = Document::new();
@@ -72,22 +79,124 @@ pub mod svg_methods {
         Element::new("filter")
     }
 
-    pub fn create_linear_gradient(vector: (f32, f32), stop: Vec<(String, Color)>, key: String) -> Element {
+    pub fn create_linear_gradient(start: (f32, f32), end: (f32, f32), stop: Vec<(String, Color)>, transform: &Transform, key: String) -> Element {
         let mut linear_gradient = Element::new("linearGradient");
         linear_gradient.assign("id", key);
-        linear_gradient.assign("x1", into_str![0]);
-        linear_gradient.assign("y1", into_str![0]);
-        linear_gradient.assign("x2", into_str![vector.0 * 100.0, "%"]);
-        linear_gradient.assign("y2", into_str![vector.1 * 100.0, "%"]);
+        linear_gradient.assign("gradientUnits", "userSpaceOnUse");
+        linear_gradient.assign("x1", into_str![start.0]);
+        linear_gradient.assign("y1", into_str![start.1]);
+        linear_gradient.assign("x2", into_str![end.0]);
+        linear_gradient.assign("y2", into_str![end.1]);
+        linear_gradient.assign("gradientTransform", create_matrix(transform));
+        append_stops(&mut linear_gradient, stop);
+        linear_gradient
+    }
+
+    pub fn create_radial_gradient(start: (f32, f32, f32), end: (f32, f32, f32), stop: Vec<(String, Color)>, transform: &Transform, key: String) -> Element {
+        let mut radial_gradient = Element::new("radialGradient");
+        radial_gradient.assign("id", key);
+        radial_gradient.assign("gradientUnits", "userSpaceOnUse");
+        radial_gradient.assign("fx", into_str![start.0]);
+        radial_gradient.assign("fy", into_str![start.1]);
+        radial_gradient.assign("fr", into_str![start.2]);
+        radial_gradient.assign("cx", into_str![end.0]);
+        radial_gradient.assign("cy", into_str![end.1]);
+        radial_gradient.assign("r", into_str![end.2]);
+        radial_gradient.assign("gradientTransform", create_matrix(transform));
+        append_stops(&mut radial_gradient, stop);
+        radial_gradient
+    }
+
+    /// SVG has no conic gradient, so it is drawn as a pattern of solid color wedges
+    /// covering the whole `size` area. That is `CONIC_WEDGES` paths, so the render reuses
+    /// the pattern for every fill and stroke with the same gradient.
+    pub fn create_conic_gradient(center: (f32, f32), angle: f32, stop: &[GradientStop], transform: &Transform, size: (f32, f32), key: String) -> Element {
+        let mut pattern = Element::new("pattern");
+        pattern.assign("id", key);
+        pattern.assign("patternUnits", "userSpaceOnUse");
+        pattern.assign("x", into_str![0]);
+        pattern.assign("y", into_str![0]);
+        pattern.assign("width", into_str![size.0]);
+        pattern.assign("height", into_str![size.1]);
+
+        let Transform { a, b, c, d, .. } = *transform;
+        let det = (a * d - b * c).abs();
+        if det == 0.0 {
+            return pattern;
+        }
+        // the wedges must reach every corner once mapped to the device
+        let (dx, dy) = transform.apply(center.0, center.1);
+        let distance = [(0.0, 0.0), (size.0, 0.0), (0.0, size.1), size]
+            .iter()
+            .map(|(x, y)| ((x - dx).powi(2) + (y - dy).powi(2)).sqrt())
+            .fold(0.0f32, f32::max);
+        let radius = distance * (a * a + b * b + c * c + d * d).sqrt() / det + 1.0;
+
+        let step = 1.0 / CONIC_WEDGES as f32;
+        for i in 0..CONIC_WEDGES {
+            let t = i as f32 * step;
+            // overlap the next wedge a little to hide anti-aliasing seams
+            let end = if i + 1 == CONIC_WEDGES { t + step } else { t + step * 1.5 };
+            let point = |t: f32| {
+                let theta = angle.to_radians() + t * 2.0 * std::f32::consts::PI;
+                (center.0 + radius * theta.cos(), center.1 + radius * theta.sin())
+            };
+            let mut wedge = PathData::new();
+            wedge.move_to(center.0, center.1);
+            let (x, y) = point(t);
+            wedge.line_to(x, y);
+            let (x, y) = point(end);
+            wedge.line_to(x, y);
+            wedge.close();
+            wedge.transform(*transform);
+
+            let color = interpolate_stops(stop, t + step / 2.0);
+            let color_u8 = color.to_color_u8();
+            let mut path = Element::new("path");
+            path.assign("d", String::from(&wedge));
+            path.assign("fill", into_str!["rgb(", color_u8.red(), ", ", color_u8.green(), ", ", color_u8.blue(), ")"]);
+            path.assign("fill-opacity", into_str![color.alpha()]);
+            pattern.append(path);
+        }
+        pattern
+    }
+
+    pub fn create_png_data_url(data: &[u8], width: u32, height: u32) -> Option<String> {
+        let png = PixmapRef::from_bytes(data, width, height)?.encode_png().ok()?;
+        Some(into_str!["data:image/png;base64,", base64::encode(png)])
+    }
+
+    /// `tile` is the pattern cell as (x, y, width, height), the image is drawn at its origin.
+    pub fn create_pattern(href: &str, size: (f32, f32), tile: (f32, f32, f32, f32), transform: &Transform, key: String) -> Element {
+        let mut pattern = Element::new("pattern");
+        pattern.assign("id", key);
+        pattern.assign("patternUnits", "userSpaceOnUse");
+        pattern.assign("x", into_str![tile.0]);
+        pattern.assign("y", into_str![tile.1]);
+        pattern.assign("width", into_str![tile.2]);
+        pattern.assign("height", into_str![tile.3]);
+        pattern.assign("patternTransform", create_matrix(transform));
+        let mut image = create_image_tag(href);
+        image.assign("width", into_str![size.0]);
+        image.assign("height", into_str![size.1]);
+        pattern.append(image);
+        pattern
+    }
+
+    pub fn create_matrix(ts: &Transform) -> String {
+        let Transform { a, b, c, d, e, f } = ts;
+        into_str!["matrix(", a, " ", b, " ", c, " ", d, " ", e, " ", f, ")"]
+    }
+
+    fn append_stops(gradient: &mut Element, stop: Vec<(String, Color)>) {
         for (key, value) in stop {
             let mut stop = Element::new("stop");
-            let Color { r, g, b, a } = value;
-            stop.assign("offset", key.clone());
-            stop.assign("stop-color", into_str![r.to_u8(), g.to_u8(), b.to_u8()]);
-            stop.assign("stop-opacity", into_str![a.to_u8()]);
-            linear_gradient.append(stop);
+            let color = value.to_color_u8();
+            stop.assign("offset", key);
+            stop.assign("stop-color", into_str!["rgb(", color.red(), ", ", color.green(), ", ", color.blue(), ")"]);
+            stop.assign("stop-opacity", into_str![value.alpha()]);
+            gradient.append(stop);
         }
-        linear_gradient
     }
 
     pub fn create_use_tag(id: String) -> Element {
@@ -176,8 +285,8 @@ use svg::node::element::Element;
 use crate::paint::FillRule;
 use crate::backend::PainterBackend;
 use crate::f32x2;
-use crate::operate::Segment;
-use crate::paint::shader::Shader;
+use crate::operate::{PixelSegment, Segment};
+use crate::paint::shader::{ConicGradient, Shader};
 use crate::paint::Paint;
 use crate::paint::stroke::{lint_cap_to_string, lint_join_to_string, Stroke};
 use crate::path::PathData;
@@ -190,6 +299,8 @@ pub struct SvgRender {
     pub height: f32,
     pub view_box: (f32, f32, f32, f32),
     use_count: usize,
+    /// The ids of the conic gradient patterns already in `defs`.
+    conic_patterns: Vec<(ConicGradient, String)>,
 }
 
 impl Default for SvgRender {
@@ -208,6 +319,7 @@ impl SvgRender {
             height,
             view_box: (0.0, 0.0, width, height),
             use_count: 0,
+            conic_patterns: vec![],
         }
     }
 
@@ -215,6 +327,8 @@ impl SvgRender {
         self.width = size.x();
         self.height = size.y();
         self.view_box = view_box.unwrap_or((0.0, 0.0, self.width, self.height));
+        // the patterns cover the old size
+        self.conic_patterns.clear();
         let (x, y, w, h) = &self.view_box;
         self.svg.assign("width", into_str![&self.width]);
         self.svg.assign("height", into_str![&self.width]);
@@ -225,13 +339,13 @@ impl SvgRender {
         let mut path_tag = create_path_tag(&String::from(path_data));
         let fill_rule = fill_rule.unwrap_or(FillRule::Nonzero);
         path_tag.assign("fill-rule", String::from(fill_rule));
-        let (fill_url, fill_defs) = paint.and_then(|p| Some(SvgRender::build_paint(p))).unwrap_or((String::from("transparent"), None));
+        let (fill_url, fill_defs) = paint.and_then(|p| Some(self.build_paint(p))).unwrap_or((String::from("transparent"), None));
         path_tag.assign("fill", fill_url);
         if let Some(node) = fill_defs {
             self.defs.append(node);
         }
         if let Some(stroke) = stroke {
-            let (stroke_url, stroke_defs) = SvgRender::build_paint(&stroke.paint);
+            let (stroke_url, stroke_defs) = self.build_paint(&stroke.paint);
             path_tag.assign("stroke", stroke_url);
             path_tag.assign("stroke-width", stroke.width);
             path_tag.assign("stroke-linejoin", lint_join_to_string(stroke.line_join));
@@ -242,12 +356,43 @@ impl SvgRender {
                 self.defs.append(node);
             }
         }
+        let path_tag = self.apply_clip(path_tag, clip, clear);
+        self.content.push(path_tag);
+    }
+
+    /// Svg has no copy compositing, so pixels written by `put_image_data` are drawn over the content.
+    pub fn append_image(&mut self, seg: &PixelSegment) -> Option<()> {
+        let href = create_png_data_url(&seg.data, seg.size.x() as u32, seg.size.y() as u32)?;
+        let mut image = create_image_tag(&href);
+        image.assign("width", into_str![seg.size.x()]);
+        image.assign("height", into_str![seg.size.y()]);
+        image.assign("transform", create_matrix(&seg.transform));
+        image.assign("opacity", into_str![seg.opacity]);
+        image.assign("image-rendering", if seg.image_smoothing { "optimizeQuality" } else { "optimizeSpeed" });
+        image.assign("preserveAspectRatio", "none");
+        // the source rect is in the pixel space of the image, so it clips the image itself
+        // and the canvas clip goes on a group around it
+        let image = match seg.src_rect.and_then(|(x, y, width, height)| PathData::create_rect(x, y, width, height)) {
+            Some(rect) => {
+                let id = self.create_use_id();
+                self.defs.append(create_clip_tag(vec![create_path_tag(&String::from(&rect))], id.clone()));
+                image.assign("clip-path", into_str!["url(#", id, ")"]);
+                group(vec![image])
+            }
+            None => image,
+        };
+        let image = self.apply_clip(image, seg.clip.clone(), seg.clear_rect.clone());
+        self.content.push(image);
+        Some(())
+    }
+
+    fn apply_clip(&mut self, mut element: Element, clip: Option<crate::paint::ClipMask>, clear: Option<crate::operate::ClearRect>) -> Element {
         if let Some(clip) = clip {
             let mut clip_path = create_path_tag(&String::from(&clip.path));
             clip_path.assign("fill-rule", String::from(clip.fill_rule.clone()));
             let id = self.create_use_id();
             let clip_tag = create_clip_tag(vec![clip_path], id.clone());
-            path_tag.assign("clip-path", into_str!["url(#", id, ")"]);
+            element.assign("clip-path", into_str!["url(#", id, ")"]);
             self.defs.append(clip_tag);
         }
         if let Some(clear) = clear {
@@ -255,15 +400,11 @@ impl SvgRender {
             clip_path.assign("fill-rule", String::from(FillRule::default()));
             let id = self.create_use_id();
             let clip_tag = create_clip_tag(vec![clip_path], id.clone());
-            path_tag = group(vec![path_tag]);
-            path_tag.assign("clip-path", into_str!["url(#", id, ")"]);
+            element = group(vec![element]);
+            element.assign("clip-path", into_str!["url(#", id, ")"]);
             self.defs.append(clip_tag);
         }
-        self.content.push(path_tag);
-    }
-
-    pub fn append_image(&mut self, image_url: &str) {
-        create_image_tag(image_url);
+        element
     }
 
     fn create_use_id(&mut self) -> String {
@@ -271,8 +412,8 @@ impl SvgRender {
         format!("use_id_{}", self.use_count)
     }
 
-    fn build_paint(paint: &Paint) -> (String, Option<Element>) {
-        match paint.shader {
+    fn build_paint(&mut self, paint: &Paint) -> (String, Option<Element>) {
+        match &paint.shader {
             Shader::SolidColor(color) => {
                 let color = color.to_color_u8();
                 let r = color.red();
@@ -281,9 +422,85 @@ impl SvgRender {
                 let a = color.alpha();
                 (format!("rgba({}, {}, {}, {})", r, g, b, a as f32 / 256.0), None)
             }
-            Shader::LinearGradient(_) => { todo!() }
-            Shader::RadialGradient(_) => { todo!() }
-            Shader::Pattern => { todo!() }
+            Shader::LinearGradient(gradient) => {
+                if gradient.is_degenerate() {
+                    return (String::from("transparent"), None);
+                }
+                let id = self.create_use_id();
+                let stops = gradient.stops.iter().map(|stop| (into_str![stop.offset], stop.color)).collect();
+                let element = create_linear_gradient(
+                    (gradient.x0, gradient.y0),
+                    (gradient.x1, gradient.y1),
+                    stops,
+                    &gradient.transform,
+                    id.clone(),
+                );
+                (into_str!["url(#", id, ")"], Some(element))
+            }
+            Shader::RadialGradient(gradient) => {
+                if gradient.is_degenerate() {
+                    return (String::from("transparent"), None);
+                }
+                let id = self.create_use_id();
+                let stops = gradient.stops.iter().map(|stop| (into_str![stop.offset], stop.color)).collect();
+                let element = create_radial_gradient(
+                    (gradient.x0, gradient.y0, gradient.r0),
+                    (gradient.x1, gradient.y1, gradient.r1),
+                    stops,
+                    &gradient.transform,
+                    id.clone(),
+                );
+                (into_str!["url(#", id, ")"], Some(element))
+            }
+            Shader::ConicGradient(gradient) => {
+                if gradient.is_degenerate() {
+                    return (String::from("transparent"), None);
+                }
+                if let Some((_, id)) = self.conic_patterns.iter().find(|(known, _)| known == gradient) {
+                    return (into_str!["url(#", id, ")"], None);
+                }
+                let id = self.create_use_id();
+                self.conic_patterns.push((gradient.clone(), id.clone()));
+                let element = create_conic_gradient(
+                    (gradient.cx, gradient.cy),
+                    gradient.angle,
+                    &gradient.stops,
+                    &gradient.transform,
+                    (self.width, self.height),
+                    id.clone(),
+                );
+                (into_str!["url(#", id, ")"], Some(element))
+            }
+            Shader::Pattern(pattern) => {
+                let (width, height) = (pattern.size.x(), pattern.size.y());
+                let transform = pattern.get_transform();
+                let href = create_png_data_url(&pattern.data, pattern.width(), pattern.height());
+                let (inverse, href) = match transform.invert().zip(href) {
+                    Some(v) => v,
+                    None => return (String::from("transparent"), None),
+                };
+                // svg patterns always repeat, so a non-repeating axis gets a cell
+                // long enough to push the other copies out of the canvas
+                let corners = [(0.0, 0.0), (self.width, 0.0), (0.0, self.height), (self.width, self.height)]
+                    .map(|(x, y)| inverse.apply(x, y));
+                let cell = |size: f32, repeat: bool, axis: fn(&(f32, f32)) -> f32| {
+                    if repeat {
+                        return size;
+                    }
+                    let min = corners.iter().map(axis).fold(f32::MAX, f32::min);
+                    let max = corners.iter().map(axis).fold(f32::MIN, f32::max);
+                    max.max(size - min).max(size) + 1.0
+                };
+                let tile = (
+                    0.0,
+                    0.0,
+                    cell(width, pattern.repetition.repeat_x(), |p| p.0),
+                    cell(height, pattern.repetition.repeat_y(), |p| p.1),
+                );
+                let id = self.create_use_id();
+                let element = create_pattern(&href, (width, height), tile, &transform, id.clone());
+                (into_str!["url(#", id, ")"], Some(element))
+            }
         }
     }
 }
@@ -296,8 +513,7 @@ impl PainterBackend for SvgRender {
     fn draw(&mut self, segment: &Segment) {
         match segment {
             Segment::Pixel(ref seg) => {
-                todo!()
-                // self.append_image()
+                self.append_image(seg)
             }
             Segment::Vector(ref seg) => {
                 seg.fill.as_ref().and_then(|paint| Some(self.append_path(&seg.path, Some(paint), None, Some(seg.fill_rule), seg.clip.clone(), seg.clear_rect.clone())));
@@ -1,15 +1,21 @@
 use std::f32::consts::PI;
+use std::cell::RefCell;
 use std::fmt::{Debug, Formatter};
 use crate::paint::{ClipMask, FillRule, Paint};
-use crate::path::{PathBuilder, PathData, quad_to_curve};
+use crate::path::{CornerRadius, PathBuilder, PathData, quad_to_curve};
 use crate::transform::Transform;
 
-use tiny_skia_path::{LineCap, LineJoin, StrokeDash};
+use tiny_skia_path::{f32x2, LineCap, LineJoin, StrokeDash};
 use crate::backend::PainterBackend;
-use crate::font::{FontBucket, Glyph};
+use crate::backend::skia_cpu::SkiaCPURender;
+use crate::font::{ColorGlyph, FaceId, FaceSelector, FontBucket, FontLoadError, FontFeature, FontKerning, FontVariation, Glyph, TextMetrics};
+use crate::font::shorthand::parse_font;
+use crate::image::{Image, ImageData};
 use crate::operate::Operates;
+use crate::paint::blend::BlendMode;
+use crate::paint::shader::{ConicGradient, LinearGradient, Pattern, RadialGradient, Repetition};
 use crate::paint::stroke::Stroke;
-use crate::style_bucket::{StyleBucket, StyleStore};
+use crate::style_bucket::{StyleBucket, StyleStore, TextAlign, TextBaseLine, TextDirection};
 
 
 #[derive(Default)]
@@ -20,6 +26,8 @@ pub struct Context<'a> {
     path_cache: PathBuilder,
     operate_queue: Operates,
     font_bucket: Option<&'a mut dyn FontBucket>,
+    /// The raster `get_image_data` reads from, with the count of operates already drawn into it.
+    raster: RefCell<Option<(usize, SkiaCPURender)>>,
 }
 
 impl<'a> Context<'a> {
@@ -37,6 +45,7 @@ impl<'a> Context<'a> {
     pub fn reset_wh(&mut self, width: f32, height: f32) {
         self.width = width;
         self.height = height;
+        self.raster.replace(None);
     }
 
     pub fn set_font_family(&mut self, family: &str) {
@@ -47,12 +56,62 @@ impl<'a> Context<'a> {
         self.style_bucket.font_size = size;
     }
 
+    pub fn set_font_kerning(&mut self, font_kerning: FontKerning) {
+        self.style_bucket.font_kerning = font_kerning;
+    }
+
+    /// Sets the OpenType features the font bucket applies when it shapes the text.
+    pub fn set_font_feature_settings(&mut self, features: Vec<FontFeature>) {
+        self.style_bucket.font_feature_settings = features;
+    }
+
+    /// Sets the variable font axis values, they win over the axes the font weight and stretch map to.
+    pub fn set_font_variation_settings(&mut self, variations: Vec<FontVariation>) {
+        self.style_bucket.font_variation_settings = variations;
+    }
+
+    /// Selects a face of a font collection, or a face by PostScript name, for the text.
+    pub fn set_font_face(&mut self, face: FaceSelector) {
+        self.style_bucket.font_face = face;
+    }
+
+    /// Sets the paragraph direction of the text, which also decides where `start` and `end` align.
+    pub fn set_direction(&mut self, direction: TextDirection) {
+        self.style_bucket.direction = direction;
+    }
+
+    pub fn set_text_align(&mut self, text_align: TextAlign) {
+        self.style_bucket.text_align = text_align;
+    }
+
+    pub fn set_text_base_line(&mut self, text_base_line: TextBaseLine) {
+        self.style_bucket.text_base_line = text_base_line;
+    }
+
+    /// Sets the font from a css `font` shorthand, an invalid value is ignored and returns `None`.
+    pub fn set_font(&mut self, font: &str) -> Option<()> {
+        let font = parse_font(font)?;
+        self.style_bucket.font_family = font.family;
+        self.style_bucket.font_size = font.size;
+        self.style_bucket.font_style = font.style;
+        self.style_bucket.font_variant = font.variant;
+        self.style_bucket.font_weight = font.weight;
+        self.style_bucket.font_stretch = font.stretch;
+        Some(())
+    }
+
+    /// Returns the font serialized as a css `font` shorthand.
+    pub fn get_font(&self) -> String {
+        self.style_bucket.get_font_style().to_css()
+    }
+
     pub fn set_font_bucket(&mut self, bucket: &'a mut impl FontBucket) {
         self.font_bucket = Some(bucket)
     }
 
-    pub fn load_font(&mut self, buf: &[u8]) -> Option<()> {
-        self.font_bucket.as_mut().and_then(|fb| fb.load_font(buf))
+    /// Loads a font into the font bucket and returns its faces or why it was rejected, `None` when there is no bucket.
+    pub fn load_font(&mut self, buf: &[u8]) -> Option<Result<Vec<FaceId>, FontLoadError>> {
+        self.font_bucket.as_mut().map(|fb| fb.load_font(buf))
     }
 
     pub fn set_stroke_style(&mut self, paint: Paint) {
@@ -79,6 +138,16 @@ impl<'a> Context<'a> {
         self.style_bucket.miter_limit = miter_limit
     }
 
+    pub fn set_global_alpha(&mut self, global_alpha: f32) {
+        if (0.0..=1.0).contains(&global_alpha) {
+            self.style_bucket.global_alpha = global_alpha
+        }
+    }
+
+    pub fn set_image_smoothing_enabled(&mut self, image_smoothing_enabled: bool) {
+        self.style_bucket.image_smoothing_enabled = image_smoothing_enabled
+    }
+
     pub fn set_line_dash_offset(&mut self, line_dash_offset: Option<StrokeDash>) {
         self.style_bucket.line_dash_offset = line_dash_offset
     }
@@ -86,8 +155,8 @@ impl<'a> Context<'a> {
     pub fn arc(&mut self, x: f32, y: f32, radius: f32, start_angle: f32, end_angle: f32, anticlockwise: bool) {
         self.path_cache.arc(x, y, radius, start_angle, end_angle, anticlockwise, &self.style_bucket.transform);
     }
-    pub fn arc_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) {
-        self.path_cache.arc_to(x1, y1, x2, y2, &self.style_bucket.transform);
+    pub fn arc_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, radius: f32) -> Option<()> {
+        self.path_cache.arc_to(x1, y1, x2, y2, radius, &self.style_bucket.transform)
     }
     pub fn begin_path(&mut self) {
         self.path_cache.clear();
@@ -97,6 +166,8 @@ impl<'a> Context<'a> {
     }
     pub fn clear_rect(&mut self, x: f32, y: f32, width: f32, height: f32) {
         self.operate_queue.append_clear_rect(x, y, width, height, &self.style_bucket.transform, self.width, self.height);
+        // clearing changes operates that are already drawn
+        self.raster.replace(None);
     }
     pub fn clip(&mut self, path: Option<PathData>, fill_rule: Option<FillRule>) {
         let path = path.unwrap_or(self.path_cache.clone().into_path_data());
@@ -105,26 +176,73 @@ impl<'a> Context<'a> {
     pub fn close_path(&mut self) {
         self.path_cache.close();
     }
-    pub fn create_conic_gradient() {
-        todo!()
+    pub fn create_conic_gradient(&self, start_angle: f32, x: f32, y: f32) -> ConicGradient {
+        ConicGradient::new(start_angle, x, y)
     }
-    pub fn create_image_data() {
-        todo!()
+    pub fn create_image_data(&self, width: u32, height: u32) -> Option<ImageData> {
+        ImageData::new(width, height)
     }
-    pub fn create_linear_gradient() {
-        todo!()
+    pub fn create_linear_gradient(&self, x0: f32, y0: f32, x1: f32, y1: f32) -> LinearGradient {
+        LinearGradient::new(x0, y0, x1, y1)
     }
-    pub fn create_pattern() {
-        todo!()
+    pub fn create_pattern(&self, image: &Image, repetition: Repetition) -> Option<Pattern> {
+        Pattern::new(image.data(), image.size(), repetition)
     }
-    pub fn create_radial_gradient() {
-        todo!()
+    pub fn create_radial_gradient(&self, x0: f32, y0: f32, r0: f32, x1: f32, y1: f32, r1: f32) -> Option<RadialGradient> {
+        RadialGradient::new(x0, y0, r0, x1, y1, r1)
     }
-    pub fn draw_image(&mut self) {
-        todo!()
+    /// Draws the image at (dx, dy) with its intrinsic size.
+    pub fn draw_image(&mut self, image: &Image, dx: f32, dy: f32) -> Option<()> {
+        let (width, height) = (image.size().x(), image.size().y());
+        self.draw_image_with_src_rect(image, 0.0, 0.0, width, height, dx, dy, width, height)
     }
-    pub fn ellipse(&mut self) {
-        todo!()
+    /// Draws the whole image scaled into the (dx, dy, dw, dh) rect.
+    pub fn draw_image_with_size(&mut self, image: &Image, dx: f32, dy: f32, dw: f32, dh: f32) -> Option<()> {
+        let (width, height) = (image.size().x(), image.size().y());
+        self.draw_image_with_src_rect(image, 0.0, 0.0, width, height, dx, dy, dw, dh)
+    }
+    /// Draws the (sx, sy, sw, sh) part of the image scaled into the (dx, dy, dw, dh) rect.
+    pub fn draw_image_with_src_rect(&mut self, image: &Image, sx: f32, sy: f32, sw: f32, sh: f32, dx: f32, dy: f32, dw: f32, dh: f32) -> Option<()> {
+        let (data, width, height) = (image.data(), image.width() as usize, image.height() as usize);
+        if sw == 0.0 || sh == 0.0 || dw == 0.0 || dh == 0.0 {
+            return None;
+        }
+        // negative sizes flip the rect to the other side of its origin
+        let (sx, sw) = if sw < 0.0 { (sx + sw, -sw) } else { (sx, sw) };
+        let (sy, sh) = if sh < 0.0 { (sy + sh, -sh) } else { (sy, sh) };
+        let (dx, dw) = if dw < 0.0 { (dx + dw, -dw) } else { (dx, dw) };
+        let (dy, dh) = if dh < 0.0 { (dy + dh, -dh) } else { (dy, dh) };
+
+        // the source rect is clipped to the image, the transform keeps the scale of the full rect
+        let (x0, y0) = (sx.max(0.0), sy.max(0.0));
+        let (x1, y1) = ((sx + sw).min(width as f32), (sy + sh).min(height as f32));
+        if x0 >= x1 || y0 >= y1 {
+            return None;
+        }
+        // whole pixels are copied, only the covered part of the edge pixels is drawn
+        let (left, top) = (x0.floor() as usize, y0.floor() as usize);
+        let (right, bottom) = (x1.ceil() as usize, y1.ceil() as usize);
+        let mut pixels = Vec::with_capacity((right - left) * (bottom - top) * 4);
+        for row in top..bottom {
+            pixels.extend_from_slice(&data[(row * width + left) * 4..(row * width + right) * 4]);
+        }
+
+        let mut transform = self.style_bucket.transform;
+        transform.translate(dx, dy);
+        transform.scale(dw / sw, dh / sh);
+        transform.translate(left as f32 - sx, top as f32 - sy);
+        self.operate_queue.append()
+            .pixel(&pixels, f32x2([(right - left) as f32, (bottom - top) as f32]))
+            .set_transform(transform)
+            .set_src_rect((x0 - left as f32, y0 - top as f32, x1 - x0, y1 - y0))
+            .set_opacity(self.style_bucket.global_alpha)
+            .set_image_smoothing(self.style_bucket.image_smoothing_enabled)
+            .set_clip(self.style_bucket.clip_mask.clone())
+            .finish();
+        Some(())
+    }
+    pub fn ellipse(&mut self, x: f32, y: f32, radius_x: f32, radius_y: f32, rotation: f32, start_angle: f32, end_angle: f32, anticlockwise: bool) -> Option<()> {
+        self.path_cache.ellipse(x, y, radius_x, radius_y, rotation, start_angle, end_angle, anticlockwise, &self.style_bucket.transform)
     }
     pub fn fill(&mut self, path: Option<PathData>, fill_rule: Option<FillRule>) {
         let path = path.unwrap_or(self.path_cache.clone().into_path_data());
@@ -135,26 +253,44 @@ impl<'a> Context<'a> {
         PathData::create_rect(x, y, width, height)
             .and_then(|rect| Some(self.fill(Some(rect.transform_to(self.style_bucket.transform.clone())), None)));
     }
-    pub fn fill_text<'b>(&mut self, text: &str, x: f32, y: f32, max_width: Option<f32>, mut font_bucket: Option<&'b mut dyn FontBucket>) -> Option<(PathData)> {
-        let Glyph { mut path, fill_rule, transform } = {
-            if self.font_bucket.is_some() {
-                self.font_bucket.as_mut().and_then(|mut fb| fb.get_glyph(&self.style_bucket.get_font_style(), text))
-            } else if font_bucket.is_some() {
-                font_bucket.as_mut().and_then(|mut fb| fb.get_glyph(&self.style_bucket.get_font_style(), text))
-            } else {
-                None
-            }
-        }?;
-        path.transform(transform);
-        path.transform(Transform::new_translate(x, y));
+    /// Fills the text, color glyphs are drawn with their own colors or bitmaps.
+    pub fn fill_text<'b>(&mut self, text: &str, x: f32, y: f32, max_width: Option<f32>, font_bucket: Option<&'b mut dyn FontBucket>) -> Option<(PathData)> {
+        let Glyph { path, fill_rule, colors, .. } = self.text_glyph(text, x, y, font_bucket)?;
         self.fill(Some(path.clone()), Some(fill_rule));
+        for color in colors {
+            match color {
+                ColorGlyph::Layer { path, color } => {
+                    let mut fill = self.style_bucket.get_fill();
+                    if let Some(mut color) = color {
+                        color.apply_opacity(self.style_bucket.global_alpha);
+                        fill.set_color(color);
+                    }
+                    self._fill(path, fill, FillRule::Nonzero, self.style_bucket.clip_mask.clone());
+                }
+                ColorGlyph::Image { image, transform } => {
+                    // glyph bitmaps are scaled to the font size, smoothing keeps them from looking jagged
+                    self.operate_queue.append()
+                        .pixel(image.data(), image.size())
+                        .set_transform(transform)
+                        .set_opacity(self.style_bucket.global_alpha)
+                        .set_image_smoothing(true)
+                        .set_clip(self.style_bucket.clip_mask.clone())
+                        .finish();
+                }
+            }
+        }
         Some((path))
     }
     pub fn get_context_attributes(&self) -> &StyleBucket {
         &self.style_bucket
     }
-    pub fn get_image_data(&self) {
-        todo!()
+    /// Returns the pixels of the (sx, sy, sw, sh) rect, drawing the pending operates first.
+    pub fn get_image_data(&self, sx: i32, sy: i32, sw: i32, sh: i32) -> Option<ImageData> {
+        let (sx, sw) = if sw < 0 { (sx + sw, -sw) } else { (sx, sw) };
+        let (sy, sh) = if sh < 0 { (sy + sh, -sh) } else { (sy, sh) };
+        self.flush()?;
+        let raster = self.raster.borrow();
+        raster.as_ref()?.1.get_image_data(sx, sy, sw as u32, sh as u32)
     }
     pub fn get_line_dash(&self) -> Option<StrokeDash> {
         self.style_bucket.line_dash_offset.clone()
@@ -165,14 +301,55 @@ impl<'a> Context<'a> {
     pub fn line_to(&mut self, x: f32, y: f32) {
         self.path_cache.line_to(x, y, &self.style_bucket.transform)
     }
-    pub fn measure_text() {
-        todo!()
+    /// Measures the text with the context font bucket, or `font_bucket` when the context has none.
+    pub fn measure_text<'b>(&mut self, text: &str, font_bucket: Option<&'b mut dyn FontBucket>) -> Option<TextMetrics> {
+        let style = self.style_bucket.get_font_style();
+        let metrics = match self.font_bucket.as_mut() {
+            Some(fb) => fb.measure_text(&style, text),
+            None => font_bucket?.measure_text(&style, text),
+        }?;
+        let (dx, dy) = self.style_bucket.get_text_offset(&metrics);
+        Some(metrics.relative_to(dx, dy))
     }
     pub fn move_to(&mut self, x: f32, y: f32) {
         self.path_cache.move_to(x, y, &self.style_bucket.transform)
     }
-    pub fn put_image_data(&mut self) {
-        todo!()
+    pub fn put_image_data(&mut self, image_data: &ImageData, dx: i32, dy: i32) -> Option<()> {
+        self.put_image_data_with_dirty_rect(image_data, dx, dy, 0, 0, image_data.width as i32, image_data.height as i32)
+    }
+    /// Writes the dirty rect of the image data at (dx + dirty_x, dy + dirty_y).
+    ///
+    /// The pixels replace the canvas ones, the transform, global alpha, clip and compositing are ignored.
+    pub fn put_image_data_with_dirty_rect(&mut self, image_data: &ImageData, dx: i32, dy: i32, dirty_x: i32, dirty_y: i32, dirty_width: i32, dirty_height: i32) -> Option<()> {
+        let (width, height) = (image_data.width as i32, image_data.height as i32);
+        let (mut dirty_x, mut dirty_width) = if dirty_width < 0 { (dirty_x + dirty_width, -dirty_width) } else { (dirty_x, dirty_width) };
+        let (mut dirty_y, mut dirty_height) = if dirty_height < 0 { (dirty_y + dirty_height, -dirty_height) } else { (dirty_y, dirty_height) };
+        if dirty_x < 0 {
+            dirty_width += dirty_x;
+            dirty_x = 0;
+        }
+        if dirty_y < 0 {
+            dirty_height += dirty_y;
+            dirty_y = 0;
+        }
+        dirty_width = dirty_width.min(width - dirty_x);
+        dirty_height = dirty_height.min(height - dirty_y);
+        if dirty_width <= 0 || dirty_height <= 0 {
+            return None;
+        }
+
+        let mut pixels = Vec::with_capacity((dirty_width * dirty_height * 4) as usize);
+        for row in dirty_y..dirty_y + dirty_height {
+            let start = ((row * width + dirty_x) * 4) as usize;
+            pixels.extend_from_slice(&image_data.data[start..start + dirty_width as usize * 4]);
+        }
+        let image = ImageData::from_data(pixels, dirty_width as u32, dirty_height as u32)?.to_image()?;
+        self.operate_queue.append()
+            .pixel(image.data(), image.size())
+            .set_transform(Transform::new_translate((dx + dirty_x) as f32, (dy + dirty_y) as f32))
+            .set_blend_mode(BlendMode::Source)
+            .finish();
+        Some(())
     }
     pub fn quadratic_curve_to(&mut self, cpx: f32, cpy: f32, x: f32, y: f32) {
         self.path_cache.quadratic_curve_to(cpx, cpy, x, y, &self.style_bucket.transform)
@@ -183,6 +360,9 @@ impl<'a> Context<'a> {
         self.path_cache.append_path(&mut path);
         Some(())
     }
+    pub fn round_rect(&mut self, x: f32, y: f32, width: f32, height: f32, radii: &[CornerRadius]) -> Option<()> {
+        self.path_cache.round_rect(x, y, width, height, radii, &self.style_bucket.transform)
+    }
     pub fn reset_transform(&mut self) {
         self.set_transform(&Transform::default());
     }
@@ -209,21 +389,33 @@ impl<'a> Context<'a> {
         PathData::create_rect(x, y, width, height)
             .and_then(|rect| Some(self.stroke(Some(rect.transform_to(self.style_bucket.transform.clone())))));
     }
-    pub fn stroke_text<'b>(&mut self, text: &str, x: f32, y: f32, max_width: Option<f32>, mut font_bucket: Option<&'b mut dyn FontBucket>) -> Option<()> {
-        let Glyph { mut path, fill_rule, transform } = {
-            if self.font_bucket.is_some() {
-                self.font_bucket.as_mut().and_then(|mut fb| fb.get_glyph(&self.style_bucket.get_font_style(), text))
-            } else if font_bucket.is_some() {
-                font_bucket.as_mut().and_then(|mut fb| fb.get_glyph(&self.style_bucket.get_font_style(), text))
-            } else {
-                None
-            }
-        }?;
-        path.transform(transform);
-        path.transform(Transform::new_translate(x, y));
+    /// Strokes the outlines of the text, color glyphs have no outline to stroke.
+    pub fn stroke_text<'b>(&mut self, text: &str, x: f32, y: f32, max_width: Option<f32>, font_bucket: Option<&'b mut dyn FontBucket>) -> Option<()> {
+        let Glyph { path, .. } = self.text_glyph(text, x, y, font_bucket)?;
         self.stroke(Some(path));
         Some(())
     }
+    /// Lays the glyphs out so that (x, y) is the anchor point given by `text_align` and `text_base_line`.
+    fn text_glyph<'b>(&mut self, text: &str, x: f32, y: f32, font_bucket: Option<&'b mut dyn FontBucket>) -> Option<Glyph> {
+        let style = self.style_bucket.get_font_style();
+        let fb: &mut dyn FontBucket = match self.font_bucket.as_mut() {
+            Some(fb) => &mut **fb,
+            None => font_bucket?,
+        };
+        let (mut glyph, metrics) = fb.layout_text(&style, text)?;
+        let (dx, dy) = self.style_bucket.get_text_offset(&metrics);
+        let mut transform = Transform::new_translate(x + dx, y + dy);
+        transform.append(&glyph.transform);
+        glyph.path.transform(transform);
+        for color in glyph.colors.iter_mut() {
+            match color {
+                ColorGlyph::Layer { path, .. } => path.transform(transform),
+                ColorGlyph::Image { transform: image_transform, .. } => image_transform.prepend(&transform),
+            }
+        }
+        glyph.transform = Transform::default();
+        Some(glyph)
+    }
     pub fn transform(&mut self, ts: &Transform) {
         self.style_bucket.transform(ts);
     }
@@ -236,6 +428,7 @@ impl<'a> Context<'a> {
             .vector()
             .set_path(path)
             .set_stroke(stroke)
+            .set_image_smoothing(self.style_bucket.image_smoothing_enabled)
             .set_clip(clip_mask)
             .finish();
     }
@@ -246,12 +439,31 @@ impl<'a> Context<'a> {
             .set_path(path)
             .set_fill(fill)
             .set_fill_rule(fill_rule)
+            .set_image_smoothing(self.style_bucket.image_smoothing_enabled)
             .set_clip(clip_mask)
             .finish();
     }
 
     // fn _text(&mut self, )
 
+    /// Draws the operates appended since the last flush into the raster.
+    fn flush(&self) -> Option<()> {
+        let mut raster = self.raster.borrow_mut();
+        if raster.is_none() {
+            let (width, height) = (self.width.ceil() as u32, self.height.ceil() as u32);
+            if width == 0 || height == 0 {
+                return None;
+            }
+            *raster = Some((0, SkiaCPURender::new(width, height)));
+        }
+        let (flushed, render) = raster.as_mut()?;
+        for seg in self.operate_queue.iter().skip(*flushed) {
+            render.draw(seg);
+        }
+        *flushed = self.operate_queue.len();
+        Some(())
+    }
+
     pub fn render(&self, mut backend: Box<dyn PainterBackend>) -> Vec<u8> {
         for seg in (&self.operate_queue).iter() {
             backend.as_mut().draw(seg);
@@ -272,7 +484,18 @@ impl Debug for Context<'static> {
 
 #[cfg(test)]
 mod test {
+    use crate::backend::skia_cpu::{ImageDataRender, SkiaCPURender};
+    use crate::backend::svg::SvgRender;
     use crate::context2d::Context;
+    use crate::font::{FaceId, FontBucket, FontLoadError, FontStyles, Glyph, LoadedFace, TextMetrics};
+    use crate::image::Image;
+    use crate::f32x2;
+    use crate::paint::Paint;
+    use crate::PathData;
+    use crate::path::PathSegment;
+    use crate::paint::color::Color;
+    use crate::paint::shader::Repetition;
+    use crate::style_bucket::{TextAlign, TextBaseLine, TextDirection};
     use crate::transform::Transform;
 
     #[test]
@@ -285,4 +508,435 @@ mod test {
         };
         println!("{:?}", &ctx);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn linear_gradient() {
+        let mut ctx = Context::new_wh(100.0, 10.0);
+        ctx.translate(50.0, 0.0);
+        let mut gradient = ctx.create_linear_gradient(0.0, 0.0, 40.0, 0.0);
+        gradient.add_color_stop(0.0, Color::from_rgba8(255, 0, 0, 255));
+        gradient.add_color_stop(1.0, Color::from_rgba8(0, 0, 255, 255));
+        assert!(gradient.add_color_stop(1.5, Color::BLACK).is_none());
+        ctx.set_fill_style(Paint::from_shader(gradient));
+        ctx.fill_rect(-50.0, 0.0, 100.0, 10.0);
+
+        let data = ctx.render(Box::new(ImageDataRender(SkiaCPURender::new(100, 10))));
+        let pixel = |x: usize| &data[(5 * 100 + x) * 4..(5 * 100 + x) * 4 + 4];
+        assert_eq!(pixel(20), &[255, 0, 0, 255]);
+        assert_eq!(pixel(95), &[0, 0, 255, 255]);
+
+        let svg = String::from_utf8(ctx.render(Box::new(SvgRender::new(100.0, 10.0)))).unwrap();
+        assert!(svg.contains("gradientUnits=\"userSpaceOnUse\""));
+        assert!(svg.contains("gradientTransform=\"matrix(1 0 0 1 50 0)\""));
+    }
+
+    #[test]
+    fn radial_gradient() {
+        assert!(Context::new().create_radial_gradient(0.0, 0.0, -1.0, 0.0, 0.0, 10.0).is_none());
+
+        let mut ctx = Context::new_wh(100.0, 100.0);
+        let mut gradient = ctx.create_radial_gradient(50.0, 50.0, 20.0, 50.0, 50.0, 40.0).unwrap();
+        gradient.add_color_stop(0.0, Color::from_rgba8(255, 0, 0, 255));
+        gradient.add_color_stop(1.0, Color::from_rgba8(0, 0, 255, 255));
+        ctx.set_fill_style(Paint::from_shader(gradient));
+        ctx.fill_rect(0.0, 0.0, 100.0, 100.0);
+
+        let data = ctx.render(Box::new(ImageDataRender(SkiaCPURender::new(100, 100))));
+        let pixel = |x: usize, y: usize| &data[(y * 100 + x) * 4..(y * 100 + x) * 4 + 4];
+        assert_eq!(pixel(50, 50), &[255, 0, 0, 255]);
+        assert_eq!(pixel(1, 1), &[0, 0, 255, 255]);
+        let middle = pixel(80, 50);
+        assert!(middle[0] > 100 && middle[2] > 100);
+
+        let svg = String::from_utf8(ctx.render(Box::new(SvgRender::new(100.0, 100.0)))).unwrap();
+        assert!(svg.contains("<radialGradient"));
+        assert!(svg.contains("fr=\"20\""));
+    }
+
+    #[test]
+    fn conic_gradient() {
+        let mut ctx = Context::new_wh(100.0, 100.0);
+        let mut gradient = ctx.create_conic_gradient(0.0, 50.0, 50.0);
+        gradient.add_color_stop(0.0, Color::from_rgba8(255, 0, 0, 255));
+        gradient.add_color_stop(0.5, Color::from_rgba8(0, 0, 255, 255));
+        gradient.add_color_stop(1.0, Color::from_rgba8(255, 0, 0, 255));
+        ctx.set_fill_style(Paint::from_shader(gradient));
+        ctx.fill_rect(0.0, 0.0, 100.0, 100.0);
+
+        let data = ctx.render(Box::new(ImageDataRender(SkiaCPURender::new(100, 100))));
+        let pixel = |x: usize, y: usize| &data[(y * 100 + x) * 4..(y * 100 + x) * 4 + 4];
+        // small fills and strokes only rasterize their own area, at the same device position
+        let mut small = Context::new_wh(100.0, 100.0);
+        small.set_fill_style(ctx.get_context_attributes().fill.clone());
+        small.set_stroke_style(ctx.get_context_attributes().fill.clone());
+        small.set_line_width(4.0);
+        small.fill_rect(85.0, 45.0, 10.0, 10.0);
+        small.stroke_rect(10.0, 80.0, 10.0, 10.0);
+        let small_data = small.render(Box::new(ImageDataRender(SkiaCPURender::new(100, 100))));
+        for (x, y) in [(90, 50), (86, 54), (10, 85), (20, 90)] {
+            assert_eq!(&small_data[(y * 100 + x) * 4..(y * 100 + x) * 4 + 4], pixel(x, y));
+        }
+        assert_eq!(small_data[(50 * 100 + 50) * 4 + 3], 0);
+        let right = pixel(95, 50);
+        assert!(right[0] > 240 && right[2] < 15);
+        let left = pixel(5, 50);
+        assert!(left[0] < 15 && left[2] > 240);
+        let bottom = pixel(50, 95);
+        assert!(bottom[0] > 100 && bottom[2] > 100);
+
+        ctx.fill_rect(10.0, 10.0, 20.0, 20.0);
+        let svg = String::from_utf8(ctx.render(Box::new(SvgRender::new(100.0, 100.0)))).unwrap();
+        assert_eq!(svg.matches("<pattern").count(), 1);
+        assert_eq!(svg.matches("fill-opacity").count(), 360);
+    }
+
+    #[test]
+    fn pattern() {
+        let mut ctx = Context::new_wh(10.0, 10.0);
+        let image = Image::new(vec![255, 0, 0, 255, 0, 0, 255, 255], f32x2([2.0, 1.0])).unwrap();
+        let mut pattern = ctx.create_pattern(&image, Repetition::RepeatX).unwrap();
+        pattern.set_transform(&Transform::new_translate(0.0, 4.0));
+        ctx.set_fill_style(Paint::from_shader(pattern));
+        ctx.fill_rect(0.0, 0.0, 10.0, 10.0);
+
+        let data = ctx.render(Box::new(ImageDataRender(SkiaCPURender::new(10, 10))));
+        let pixel = |x: usize, y: usize| &data[(y * 10 + x) * 4..(y * 10 + x) * 4 + 4];
+        assert_eq!(pixel(6, 4), &[255, 0, 0, 255]);
+        assert_eq!(pixel(7, 4), &[0, 0, 255, 255]);
+        assert_eq!(pixel(6, 5), &[0, 0, 0, 0]);
+        assert_eq!(pixel(6, 3), &[0, 0, 0, 0]);
+
+        let svg = String::from_utf8(ctx.render(Box::new(SvgRender::new(10.0, 10.0)))).unwrap();
+        assert!(svg.contains("<pattern"));
+        assert!(svg.contains("data:image/png;base64,"));
+        assert!(svg.contains("patternTransform=\"matrix(1 0 0 1 0 4)\""));
+
+        // scaled patterns blend neighbouring pixels only with image smoothing
+        let image = Image::new([255, 0, 0, 255, 0, 0, 255, 255].repeat(2), f32x2([2.0, 2.0])).unwrap();
+        let render = |image_smoothing: bool| {
+            let mut ctx = Context::new_wh(10.0, 10.0);
+            ctx.set_image_smoothing_enabled(image_smoothing);
+            for (repetition, y) in [(Repetition::Repeat, 0.0), (Repetition::NoRepeat, 2.0)] {
+                let mut pattern = ctx.create_pattern(&image, repetition).unwrap();
+                pattern.set_transform(&Transform::new_scale(4.0, 4.0));
+                ctx.set_fill_style(Paint::from_shader(pattern));
+                ctx.fill_rect(0.0, y, 10.0, 2.0);
+            }
+            ctx.render(Box::new(ImageDataRender(SkiaCPURender::new(10, 10))))
+        };
+        let pixel = |data: &[u8], x: usize, y: usize| data[(y * 10 + x) * 4..(y * 10 + x) * 4 + 4].to_vec();
+        let data = render(false);
+        assert_eq!(pixel(&data, 3, 1), [255, 0, 0, 255]);
+        assert_eq!(pixel(&data, 3, 3), [255, 0, 0, 255]);
+        let data = render(true);
+        for y in [1, 3] {
+            let color = pixel(&data, 3, y);
+            assert!(color[0] > 128 && color[0] < 255 && color[2] > 0 && color[3] == 255, "{:?}", color);
+        }
+    }
+
+    #[test]
+    fn draw_image() {
+        let mut ctx = Context::new_wh(10.0, 10.0);
+        let image = Image::new(vec![255, 0, 0, 255, 0, 0, 255, 255], f32x2([2.0, 1.0])).unwrap();
+        assert!(ctx.draw_image_with_size(&image, 0.0, 0.0, 0.0, 2.0).is_none());
+        ctx.draw_image(&image, 1.0, 1.0).unwrap();
+        ctx.draw_image_with_size(&image, 0.0, 4.0, 4.0, 2.0).unwrap();
+        ctx.set_global_alpha(0.5);
+        ctx.draw_image_with_src_rect(&image, 1.0, 0.0, 1.0, 1.0, 8.0, 8.0, 2.0, 2.0).unwrap();
+
+        let data = ctx.render(Box::new(ImageDataRender(SkiaCPURender::new(10, 10))));
+        let pixel = |x: usize, y: usize| &data[(y * 10 + x) * 4..(y * 10 + x) * 4 + 4];
+        assert_eq!(pixel(1, 1), &[255, 0, 0, 255]);
+        assert_eq!(pixel(2, 1), &[0, 0, 255, 255]);
+        assert_eq!(pixel(1, 5), &[255, 0, 0, 255]);
+        assert_eq!(pixel(3, 5), &[0, 0, 255, 255]);
+        assert_eq!(pixel(1, 6), &[0, 0, 0, 0]);
+        assert_eq!(pixel(9, 9)[3], 128);
+        assert_eq!(pixel(7, 9), &[0, 0, 0, 0]);
+
+        let svg = String::from_utf8(ctx.render(Box::new(SvgRender::new(10.0, 10.0)))).unwrap();
+        assert!(svg.contains("<image"));
+        assert!(svg.contains("data:image/png;base64,"));
+        assert!(svg.contains("opacity=\"0.5\""));
+
+        // half of each pixel is drawn, nothing lands outside of the destination rect
+        let mut ctx = Context::new_wh(20.0, 20.0);
+        ctx.draw_image_with_src_rect(&image, 0.5, 0.0, 1.0, 1.0, 5.0, 5.0, 10.0, 10.0).unwrap();
+        let data = ctx.render(Box::new(ImageDataRender(SkiaCPURender::new(20, 20))));
+        let pixel = |x: usize, y: usize| &data[(y * 20 + x) * 4..(y * 20 + x) * 4 + 4];
+        assert_eq!(pixel(5, 5), &[255, 0, 0, 255]);
+        assert_eq!(pixel(9, 14), &[255, 0, 0, 255]);
+        assert_eq!(pixel(10, 5), &[0, 0, 255, 255]);
+        assert_eq!(pixel(14, 14), &[0, 0, 255, 255]);
+        assert_eq!(pixel(4, 5), &[0, 0, 0, 0]);
+        assert_eq!(pixel(15, 5), &[0, 0, 0, 0]);
+        assert_eq!(pixel(5, 4), &[0, 0, 0, 0]);
+        assert_eq!(pixel(5, 15), &[0, 0, 0, 0]);
+        let svg = String::from_utf8(ctx.render(Box::new(SvgRender::new(20.0, 20.0)))).unwrap();
+        assert!(svg.contains("<clipPath"));
+    }
+
+    #[test]
+    fn global_alpha() {
+        let mut ctx = Context::new_wh(10.0, 10.0);
+        ctx.set_global_alpha(0.5);
+        ctx.set_fill_style(Paint::from_color(Color::from_rgba8(255, 0, 0, 255)));
+        ctx.fill_rect(0.0, 0.0, 2.0, 2.0);
+        ctx.set_stroke_style(Paint::from_color(Color::from_rgba8(0, 0, 255, 255)));
+        ctx.set_line_width(2.0);
+        ctx.stroke_rect(5.0, 5.0, 4.0, 4.0);
+        let mut gradient = ctx.create_linear_gradient(0.0, 0.0, 10.0, 0.0);
+        gradient.add_color_stop(0.0, Color::from_rgba8(0, 255, 0, 255));
+        gradient.add_color_stop(1.0, Color::from_rgba8(0, 255, 0, 255));
+        ctx.set_fill_style(Paint::from_shader(gradient));
+        ctx.fill_rect(0.0, 3.0, 2.0, 1.0);
+        let pattern = ctx.create_pattern(&Image::new(vec![255, 255, 255, 255], f32x2([1.0, 1.0])).unwrap(), Repetition::Repeat).unwrap();
+        ctx.set_fill_style(Paint::from_shader(pattern));
+        ctx.fill_rect(3.0, 0.0, 1.0, 1.0);
+
+        let data = ctx.render(Box::new(ImageDataRender(SkiaCPURender::new(10, 10))));
+        let pixel = |x: usize, y: usize| &data[(y * 10 + x) * 4..(y * 10 + x) * 4 + 4];
+        // the render is premultiplied, so the painted channel drops with the alpha
+        assert_eq!(pixel(1, 1), &[128, 0, 0, 128]);
+        assert_eq!(pixel(5, 7), &[0, 0, 128, 128]);
+        assert_eq!(pixel(1, 3), &[0, 128, 0, 128]);
+        assert_eq!(pixel(3, 0), &[128, 128, 128, 128]);
+    }
+
+    #[test]
+    fn image_data() {
+        let mut ctx = Context::new_wh(10.0, 10.0);
+        ctx.set_fill_style(Paint::from_color(Color::from_rgba8(255, 0, 0, 255)));
+        ctx.fill_rect(0.0, 0.0, 4.0, 4.0);
+        let image_data = ctx.get_image_data(2, 2, 4, 4).unwrap();
+        assert_eq!((image_data.width, image_data.height), (4, 4));
+        assert_eq!(&image_data.data[0..4], &[255, 0, 0, 255]);
+        assert_eq!(&image_data.data[8..12], &[0, 0, 0, 0]);
+        assert_eq!(ctx.get_image_data(-1, 0, 1, 1).unwrap().data, vec![0, 0, 0, 0]);
+        assert_eq!(ctx.get_image_data(4, 4, -1, -1).unwrap().data, vec![255, 0, 0, 255]);
+
+        // put_image_data replaces pixels, ignoring the clip
+        ctx.clip(PathData::create_rect(5.0, 5.0, 1.0, 1.0), None);
+        let mut image_data = ctx.create_image_data(2, 2).unwrap();
+        image_data.data.copy_from_slice(&[0, 0, 255, 128, 0, 0, 255, 255, 0, 255, 0, 255, 0, 255, 0, 255]);
+        ctx.put_image_data_with_dirty_rect(&image_data, 0, 0, 0, 0, 2, 1).unwrap();
+        assert!(ctx.put_image_data_with_dirty_rect(&image_data, 0, 0, 2, 0, 1, 1).is_none());
+        let image_data = ctx.get_image_data(0, 0, 3, 2).unwrap();
+        assert_eq!(&image_data.data[0..4], &[0, 0, 255, 128]);
+        assert_eq!(&image_data.data[4..12], &[0, 0, 255, 255, 255, 0, 0, 255]);
+        assert_eq!(&image_data.data[12..16], &[255, 0, 0, 255]);
+
+        ctx.clear_rect(0.0, 0.0, 10.0, 10.0);
+        assert_eq!(ctx.get_image_data(1, 1, 1, 1).unwrap().data, vec![0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn ellipse() {
+        let mut ctx = Context::new_wh(20.0, 20.0);
+        assert!(ctx.ellipse(10.0, 10.0, -1.0, 2.0, 0.0, 0.0, 360.0, false).is_none());
+        ctx.ellipse(10.0, 10.0, 8.0, 4.0, 90.0, 0.0, 360.0, false).unwrap();
+        let path = ctx.path_cache.clone().into_path_data();
+        assert_eq!(path.len(), 5);
+        let bbox = path.get_bounding_box().unwrap();
+        assert!((bbox.get_width() - 8.0).abs() < 0.01);
+        assert!((bbox.get_height() - 16.0).abs() < 0.01);
+
+        // anticlockwise from 0 to 90 degrees sweeps the other three quarters, joined by a line
+        ctx.ellipse(0.0, 0.0, 2.0, 2.0, 0.0, 0.0, 90.0, true).unwrap();
+        let path = ctx.path_cache.clone().into_path_data();
+        assert_eq!(path.len(), 9);
+        assert!(matches!(path[5], PathSegment::LineTo { x, y } if x == 2.0 && y == 0.0));
+        assert!(matches!(path[8], PathSegment::CurveTo { x, y, .. } if x.abs() < 0.001 && (y - 2.0).abs() < 0.001));
+
+        ctx.translate(2.0, 0.0);
+        ctx.transform(&Transform::new(2.0, 0.0, 0.0, 1.0, 0.0, 0.0));
+        ctx.begin_path();
+        ctx.ellipse(0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 180.0, false).unwrap();
+        let path = ctx.path_cache.clone().into_path_data();
+        assert!(matches!(path[0], PathSegment::MoveTo { x, y } if x == 4.0 && y == 0.0));
+        assert!(matches!(path[2], PathSegment::CurveTo { x, y, .. } if x.abs() < 0.001 && y.abs() < 0.001));
+    }
+
+    #[test]
+    fn round_rect() {
+        let mut ctx = Context::new_wh(20.0, 20.0);
+        assert!(ctx.round_rect(0.0, 0.0, 10.0, 10.0, &[]).is_none());
+        assert!(ctx.round_rect(0.0, 0.0, 10.0, 10.0, &[(-1.0).into()]).is_none());
+        assert!(ctx.round_rect(0.0, 0.0, 10.0, 10.0, &[1.0.into(); 5]).is_none());
+
+        // the radii overflow the 10px sides, so they are halved
+        ctx.round_rect(2.0, 2.0, 10.0, 10.0, &[15.0.into(), (5.0, 5.0).into()]).unwrap();
+        let path = ctx.path_cache.clone().into_path_data();
+        assert!(matches!(path[0], PathSegment::MoveTo { x, y } if x == 9.5 && y == 2.0));
+        assert!(matches!(path[1], PathSegment::LineTo { x, y } if x == 9.5 && y == 2.0));
+        assert!(matches!(path[path.len() - 1], PathSegment::MoveTo { x, y } if x == 2.0 && y == 2.0));
+        let bbox = path.get_bounding_box().unwrap();
+        assert_eq!((bbox.get_width(), bbox.get_height()), (10.0, 10.0));
+
+        ctx.set_fill_style(Paint::from_color(Color::from_rgba8(255, 0, 0, 255)));
+        ctx.fill(None, None);
+        let image_data = ctx.get_image_data(0, 0, 20, 20).unwrap();
+        let alpha = |x: usize, y: usize| image_data.data[(y * 20 + x) * 4 + 3];
+        assert_eq!(alpha(2, 2), 0);
+        assert_eq!(alpha(7, 7), 255);
+        assert_eq!(alpha(11, 2), 0);
+        assert_eq!(alpha(11, 11), 0);
+    }
+
+    #[test]
+    fn arc_to() {
+        let mut ctx = Context::new_wh(20.0, 20.0);
+        assert!(ctx.arc_to(10.0, 0.0, 10.0, 10.0, -1.0).is_none());
+        // an empty path only gets the first point
+        ctx.arc_to(10.0, 0.0, 10.0, 10.0, 5.0).unwrap();
+        assert!(matches!(ctx.path_cache.0[0], PathSegment::MoveTo { x, y } if x == 10.0 && y == 0.0));
+
+        ctx.begin_path();
+        ctx.move_to(0.0, 0.0);
+        ctx.arc_to(10.0, 0.0, 10.0, 10.0, 4.0).unwrap();
+        let path = ctx.path_cache.clone().into_path_data();
+        assert_eq!(path.len(), 3);
+        assert!(matches!(path[1], PathSegment::LineTo { x, y } if (x - 6.0).abs() < 0.001 && y.abs() < 0.001));
+        assert!(matches!(path[2], PathSegment::CurveTo { x, y, .. } if (x - 10.0).abs() < 0.001 && (y - 4.0).abs() < 0.001));
+
+        // collinear points and a zero radius draw a straight line to (x1, y1)
+        ctx.arc_to(10.0, 8.0, 10.0, 12.0, 4.0).unwrap();
+        ctx.arc_to(0.0, 8.0, 0.0, 0.0, 0.0).unwrap();
+        let path = ctx.path_cache.clone().into_path_data();
+        assert!(matches!(path[3], PathSegment::LineTo { x, y } if x == 10.0 && y == 8.0));
+        assert!(matches!(path[4], PathSegment::LineTo { x, y } if x == 0.0 && y == 8.0));
+
+        // the tangent circle is computed in user space
+        ctx.begin_path();
+        ctx.set_transform(&Transform::new(2.0, 0.0, 0.0, 2.0, 0.0, 0.0));
+        ctx.move_to(0.0, 5.0);
+        ctx.arc_to(0.0, 0.0, 5.0, 0.0, 2.0).unwrap();
+        let path = ctx.path_cache.clone().into_path_data();
+        assert!(matches!(path[1], PathSegment::LineTo { x, y } if x.abs() < 0.001 && (y - 4.0).abs() < 0.001));
+        assert!(matches!(path[2], PathSegment::CurveTo { x, y, .. } if (x - 4.0).abs() < 0.001 && y.abs() < 0.001));
+    }
+
+    #[test]
+    fn embolden() {
+        // outer contours grow whatever their winding, holes shrink
+        for rect in [PathData::create_rect(0.0, 0.0, 10.0, 10.0).unwrap(), PathData::create_rect_reverse(0.0, 0.0, 10.0, 10.0).unwrap()] {
+            let bbox = rect.embolden(1.0).get_bounding_box().unwrap();
+            assert_eq!((bbox.x1, bbox.y1, bbox.x2, bbox.y2), (-1.0, -1.0, 11.0, 11.0));
+        }
+        let mut ring = PathData::create_rect(0.0, 0.0, 10.0, 10.0).unwrap();
+        ring.append(&mut PathData::create_rect_reverse(3.0, 3.0, 4.0, 4.0).unwrap());
+        let ring = ring.embolden(1.0);
+        let hole = PathData(ring[ring.len() / 2..].to_vec()).get_bounding_box().unwrap();
+        assert_eq!((hole.x1, hole.y1, hole.x2, hole.y2), (4.0, 4.0, 6.0, 6.0));
+    }
+
+    #[test]
+    fn font() {
+        let mut ctx = Context::new();
+        ctx.set_font("bold 20px/1.5 Foo, serif").unwrap();
+        assert!(ctx.set_font("20px").is_none());
+        assert_eq!(ctx.get_font(), "700 20px Foo, serif");
+        let style = ctx.get_context_attributes().get_font_style();
+        assert_eq!((style.family.as_str(), style.size, style.weight.as_str()), ("Foo, serif", 20.0, "700"));
+    }
+
+    /// Draws every char as a 10px wide box from 8px above to 2px below the baseline.
+    struct BoxFont;
+
+    impl FontBucket for BoxFont {
+        fn default_glyph(&self, _style: &FontStyles) -> Glyph {
+            Glyph::default()
+        }
+
+        fn get_glyph(&mut self, _style: &FontStyles, text: &str) -> Option<Glyph> {
+            let path = PathData::create_rect(0.0, -8.0, text.chars().count() as f32 * 10.0, 10.0)?;
+            Some(Glyph { path, ..Glyph::default() })
+        }
+
+        fn measure_text(&mut self, _style: &FontStyles, text: &str) -> Option<TextMetrics> {
+            let width = text.chars().count() as f32 * 10.0;
+            Some(TextMetrics {
+                width,
+                actual_bounding_box_right: width,
+                actual_bounding_box_ascent: 8.0,
+                actual_bounding_box_descent: 2.0,
+                font_bounding_box_ascent: 8.0,
+                font_bounding_box_descent: 2.0,
+                em_height_ascent: 8.0,
+                em_height_descent: 2.0,
+                hanging_baseline: 6.0,
+                alphabetic_baseline: 0.0,
+                ideographic_baseline: -2.0,
+                ..TextMetrics::default()
+            })
+        }
+
+        fn load_font(&mut self, buf: &[u8]) -> Result<Vec<FaceId>, FontLoadError> {
+            if buf.is_empty() {
+                return Err(FontLoadError::TruncatedHeader);
+            }
+            Ok(vec![FaceId(0)])
+        }
+
+        fn faces(&self) -> Vec<LoadedFace> {
+            vec![]
+        }
+
+        fn remove_face(&mut self, _id: FaceId) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn load_font() {
+        let mut ctx = Context::new_wh(10.0, 10.0);
+        assert_eq!(ctx.load_font(b"font"), None);
+        let mut font = BoxFont;
+        ctx.set_font_bucket(&mut font);
+        assert_eq!(ctx.load_font(b"font"), Some(Ok(vec![FaceId(0)])));
+        assert_eq!(ctx.load_font(b""), Some(Err(FontLoadError::TruncatedHeader)));
+    }
+
+    #[test]
+    fn text_align_and_base_line() {
+        let mut font = BoxFont;
+        let mut ctx = Context::new_wh(100.0, 100.0);
+        let mut text_box = |ctx: &mut Context, x: f32, y: f32| {
+            let bbox = ctx.fill_text("ab", x, y, None, Some(&mut font)).unwrap().get_bounding_box().unwrap();
+            (bbox.x1, bbox.y1)
+        };
+        assert_eq!(text_box(&mut ctx, 50.0, 50.0), (50.0, 42.0));
+
+        ctx.set_text_align(TextAlign::Center);
+        assert_eq!(text_box(&mut ctx, 50.0, 50.0), (40.0, 42.0));
+        ctx.set_text_align(TextAlign::Right);
+        assert_eq!(text_box(&mut ctx, 50.0, 50.0), (30.0, 42.0));
+        ctx.set_text_align(TextAlign::End);
+        assert_eq!(text_box(&mut ctx, 50.0, 50.0), (30.0, 42.0));
+        ctx.set_direction(TextDirection::Rtl);
+        assert_eq!(text_box(&mut ctx, 50.0, 50.0), (50.0, 42.0));
+        ctx.set_text_align(TextAlign::Start);
+        assert_eq!(text_box(&mut ctx, 50.0, 50.0), (30.0, 42.0));
+
+        ctx.set_text_align(TextAlign::Left);
+        ctx.set_text_base_line(TextBaseLine::Top);
+        assert_eq!(text_box(&mut ctx, 50.0, 50.0), (50.0, 50.0));
+        ctx.set_text_base_line(TextBaseLine::Bottom);
+        assert_eq!(text_box(&mut ctx, 50.0, 50.0), (50.0, 40.0));
+        ctx.set_text_base_line(TextBaseLine::Middle);
+        assert_eq!(text_box(&mut ctx, 50.0, 50.0), (50.0, 45.0));
+        ctx.set_text_base_line(TextBaseLine::Hanging);
+        assert_eq!(text_box(&mut ctx, 50.0, 50.0), (50.0, 48.0));
+        ctx.set_text_base_line(TextBaseLine::Ideographic);
+        assert_eq!(text_box(&mut ctx, 50.0, 50.0), (50.0, 40.0));
+
+        // measure_text reports distances from the anchor point
+        ctx.set_text_align(TextAlign::Center);
+        ctx.set_text_base_line(TextBaseLine::Top);
+        let metrics = ctx.measure_text("ab", Some(&mut font)).unwrap();
+        assert_eq!((metrics.actual_bounding_box_left, metrics.actual_bounding_box_right), (10.0, 10.0));
+        assert_eq!((metrics.actual_bounding_box_ascent, metrics.actual_bounding_box_descent), (0.0, 10.0));
+        assert_eq!((metrics.hanging_baseline, metrics.alphabetic_baseline), (-2.0, -8.0));
+    }
+}
@@ -1,6 +1,12 @@
 use crate::transform::Transform;
 use crate::paint::FillRule;
+use crate::paint::color::Color;
+use crate::image::Image;
 use crate::PathData;
+use crate::style_bucket::TextDirection;
+use std::fmt::{Display, Formatter};
+
+pub mod shorthand;
 
 pub use crate::ttf::Rect;
 pub use crate::ttf::Style as FontStyle;
@@ -12,6 +18,28 @@ pub struct Glyph {
     pub path: PathData,
     pub fill_rule: FillRule,
     pub transform: Transform,
+    /// Where each glyph of `path` was placed.
+    pub run: Vec<PositionedGlyph>,
+    /// The color glyphs, like emoji, drawn over `path` instead of an outline.
+    pub colors: Vec<ColorGlyph>,
+}
+
+/// A colored part of a glyph from a color font, placed like `Glyph::path`.
+#[derive(Clone, Debug)]
+pub enum ColorGlyph {
+    /// A COLR layer filled with its palette color, `None` is the current fill style.
+    Layer { path: PathData, color: Option<Color> },
+    /// An sbix or CBDT bitmap, `transform` maps the image pixels onto the text.
+    Image { image: Image, transform: Transform },
+}
+
+/// A glyph placed by the text layout, in pixels from the text start on the alphabetic baseline.
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub struct PositionedGlyph {
+    pub glyph_id: u16,
+    pub x: f32,
+    pub y: f32,
+    pub advance: f32,
 }
 
 #[derive(Default, Clone, Debug)]
@@ -23,6 +51,140 @@ pub struct FontStyles {
     pub variant: String,
     pub weight: String,
     pub line_height: f32,
+    pub kerning: FontKerning,
+    pub features: Vec<FontFeature>,
+    /// Variable font axis values, applied over the ones `weight` and `stretch` map to.
+    pub variations: Vec<FontVariation>,
+    /// Which face of a font collection the family resolves to.
+    pub face: FaceSelector,
+    /// The paragraph direction bidi reordering starts from.
+    pub direction: TextDirection,
+}
+
+/// Picks a face other than the one the family and style match, like a face of a `.ttc` collection.
+#[derive(Default, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FaceSelector {
+    /// The face the family, weight, style and stretch match.
+    #[default]
+    Family,
+    /// The face at this index in the collection the matched face was loaded from.
+    Index(u32),
+    /// The loaded face with this PostScript name, ahead of the family list.
+    PostScriptName(String),
+}
+
+/// The canvas `fontKerning` values.
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub enum FontKerning {
+    /// Left to the font bucket, which kerns when it shapes the text.
+    #[default]
+    Auto,
+    Normal,
+    None,
+}
+
+/// An OpenType feature setting, like `"liga" 0` in css `font-feature-settings`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FontFeature {
+    pub tag: [u8; 4],
+    pub value: u32,
+}
+
+/// Parses a comma separated list of quoted four character tags each followed by a value,
+/// `parse_value` builds an entry from the tag and the trimmed text after it.
+///
+/// `normal` is an empty list, returns `None` when a setting is malformed.
+fn parse_tagged_list<T>(text: &str, parse_value: impl Fn([u8; 4], &str) -> Option<T>) -> Option<Vec<T>> {
+    let text = text.trim();
+    if text == "normal" {
+        return Some(vec![]);
+    }
+    text.split(',').map(|setting| {
+        let setting = setting.trim();
+        let quote = setting.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let end = setting[1..].find(quote)? + 1;
+        let tag: [u8; 4] = setting.as_bytes()[1..end].try_into().ok()?;
+        if !tag.iter().all(|c| (0x20..=0x7e).contains(c)) {
+            return None;
+        }
+        parse_value(tag, setting[end + 1..].trim())
+    }).collect()
+}
+
+impl FontFeature {
+    /// Parses a css `font-feature-settings` value, `normal` is an empty list.
+    ///
+    /// Returns `None` when a setting is malformed.
+    pub fn parse_list(text: &str) -> Option<Vec<FontFeature>> {
+        parse_tagged_list(text, |tag, value| {
+            let value = match value {
+                "" | "on" => 1,
+                "off" => 0,
+                value => value.parse().ok()?,
+            };
+            Some(FontFeature { tag, value })
+        })
+    }
+}
+
+/// A variable font axis value, like `"wght" 650` in css `font-variation-settings`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FontVariation {
+    pub tag: [u8; 4],
+    pub value: f32,
+}
+
+impl FontVariation {
+    /// Parses a css `font-variation-settings` value, `normal` is an empty list.
+    ///
+    /// Returns `None` when a setting is malformed.
+    pub fn parse_list(text: &str) -> Option<Vec<FontVariation>> {
+        parse_tagged_list(text, |tag, value| {
+            let value = value.parse::<f32>().ok().filter(|value| value.is_finite())?;
+            Some(FontVariation { tag, value })
+        })
+    }
+}
+
+/// Text measurements like the canvas `TextMetrics`, in pixels.
+///
+/// Horizontal distances are measured from the start of the text and vertical ones from
+/// the alphabetic baseline, the `left` and `ascent` ones are positive towards left and up.
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct TextMetrics {
+    pub width: f32,
+    pub actual_bounding_box_left: f32,
+    pub actual_bounding_box_right: f32,
+    pub actual_bounding_box_ascent: f32,
+    pub actual_bounding_box_descent: f32,
+    pub font_bounding_box_ascent: f32,
+    pub font_bounding_box_descent: f32,
+    pub em_height_ascent: f32,
+    pub em_height_descent: f32,
+    pub hanging_baseline: f32,
+    pub alphabetic_baseline: f32,
+    pub ideographic_baseline: f32,
+}
+
+impl TextMetrics {
+    /// Returns the metrics measured from an anchor point placed (dx, dy) before the text start,
+    /// like the canvas `measureText` does for the current `textAlign` and `textBaseline`.
+    pub fn relative_to(&self, dx: f32, dy: f32) -> TextMetrics {
+        TextMetrics {
+            width: self.width,
+            actual_bounding_box_left: self.actual_bounding_box_left - dx,
+            actual_bounding_box_right: self.actual_bounding_box_right + dx,
+            actual_bounding_box_ascent: self.actual_bounding_box_ascent - dy,
+            actual_bounding_box_descent: self.actual_bounding_box_descent + dy,
+            font_bounding_box_ascent: self.font_bounding_box_ascent - dy,
+            font_bounding_box_descent: self.font_bounding_box_descent + dy,
+            em_height_ascent: self.em_height_ascent - dy,
+            em_height_descent: self.em_height_descent + dy,
+            hanging_baseline: self.hanging_baseline - dy,
+            alphabetic_baseline: self.alphabetic_baseline - dy,
+            ideographic_baseline: self.ideographic_baseline - dy,
+        }
+    }
 }
 
 pub trait FontBucket {
@@ -30,5 +192,105 @@ pub trait FontBucket {
 
     fn get_glyph(&mut self, style: &FontStyles, text: &str) -> Option<Glyph>;
 
-    fn load_font(&mut self, buf: &[u8]) -> Option<()>;
-}
\ No newline at end of file
+    fn measure_text(&mut self, style: &FontStyles, text: &str) -> Option<TextMetrics>;
+
+    /// Returns both `get_glyph` and `measure_text` of the text, buckets override it to lay the text out once.
+    fn layout_text(&mut self, style: &FontStyles, text: &str) -> Option<(Glyph, TextMetrics)> {
+        Some((self.get_glyph(style, text)?, self.measure_text(style, text)?))
+    }
+
+    /// Loads a font file, returning the faces it added, one per face of a collection.
+    fn load_font(&mut self, buf: &[u8]) -> Result<Vec<FaceId>, FontLoadError>;
+
+    /// Lists the loaded faces in load order.
+    fn faces(&self) -> Vec<LoadedFace>;
+
+    /// Unloads a face, `false` when it is not loaded.
+    fn remove_face(&mut self, id: FaceId) -> bool;
+}
+
+/// Identifies a face loaded into a font bucket, never reused after the face is removed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FaceId(pub u32);
+
+/// What a loaded face is, as listed by `FontBucket::faces`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoadedFace {
+    pub id: FaceId,
+    pub family: String,
+    pub post_script_name: String,
+    pub weight: u16,
+    pub style: FontStyle,
+    /// The css `font-stretch` keyword.
+    pub stretch: String,
+    pub monospaced: bool,
+    pub glyph_count: u16,
+    /// The chars the face has glyphs for, as sorted inclusive ranges.
+    pub unicode_ranges: Vec<(u32, u32)>,
+}
+
+/// Why a font file was rejected by `FontBucket::load_font`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FontLoadError {
+    /// The font file could not be read.
+    Io(std::io::ErrorKind),
+    /// Not a TrueType, OpenType, WOFF or WOFF2 file.
+    BadSignature,
+    /// The header or table directory is cut off.
+    TruncatedHeader,
+    /// A compressed table or stream failed to inflate.
+    DecompressionFailed,
+    /// A table does not match its checksum.
+    ChecksumMismatch,
+    /// A length in the file disagrees with the data, or a table is out of range or overlaps another.
+    SizeMismatch,
+    /// The wrapped font flavor is not supported.
+    UnsupportedFlavor,
+    /// The tables are readable but do not make a usable font.
+    MalformedFont,
+}
+
+impl Display for FontLoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            FontLoadError::Io(kind) => return write!(f, "font file unreadable: {}", kind),
+            FontLoadError::BadSignature => "unknown font signature",
+            FontLoadError::TruncatedHeader => "font header is truncated",
+            FontLoadError::DecompressionFailed => "font data failed to decompress",
+            FontLoadError::ChecksumMismatch => "font table checksum mismatch",
+            FontLoadError::SizeMismatch => "font table size mismatch",
+            FontLoadError::UnsupportedFlavor => "unsupported font flavor",
+            FontLoadError::MalformedFont => "malformed font",
+        };
+        f.write_str(message)
+    }
+}
+
+impl std::error::Error for FontLoadError {}
+
+#[cfg(test)]
+mod test {
+    use crate::font::{FontFeature, FontVariation};
+
+    #[test]
+    fn parse_feature_settings() {
+        assert_eq!(FontFeature::parse_list("normal"), Some(vec![]));
+        let features = FontFeature::parse_list(r#""liga" 0, 'kern', "ss01" on, "salt" 3"#).unwrap();
+        let features: Vec<(&[u8], u32)> = features.iter().map(|f| (&f.tag[..], f.value)).collect();
+        assert_eq!(features, vec![(&b"liga"[..], 0), (&b"kern"[..], 1), (&b"ss01"[..], 1), (&b"salt"[..], 3)]);
+        assert!(FontFeature::parse_list(r#""lig" 0"#).is_none());
+        assert!(FontFeature::parse_list("liga 0").is_none());
+        assert!(FontFeature::parse_list(r#""liga" -1"#).is_none());
+    }
+
+    #[test]
+    fn parse_variation_settings() {
+        assert_eq!(FontVariation::parse_list(" normal "), Some(vec![]));
+        let variations = FontVariation::parse_list(r#""wght" 650, 'wdth' 87.5,"slnt" -10"#).unwrap();
+        let variations: Vec<(&[u8], f32)> = variations.iter().map(|v| (&v.tag[..], v.value)).collect();
+        assert_eq!(variations, vec![(&b"wght"[..], 650.0), (&b"wdth"[..], 87.5), (&b"slnt"[..], -10.0)]);
+        assert!(FontVariation::parse_list(r#""wght""#).is_none());
+        assert!(FontVariation::parse_list(r#""opsz" big"#).is_none());
+        assert!(FontVariation::parse_list("wght 400").is_none());
+    }
+}
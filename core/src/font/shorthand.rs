@@ -0,0 +1,235 @@
+use crate::font::{FontStyle, FontStyles};
+
+/// The size `em`, `rem` and `%` are relative to, the default canvas font is `10px sans-serif`.
+const BASE_FONT_SIZE: f32 = 10.0;
+
+const STRETCHES: [&str; 8] = [
+    "ultra-condensed",
+    "extra-condensed",
+    "condensed",
+    "semi-condensed",
+    "semi-expanded",
+    "expanded",
+    "extra-expanded",
+    "ultra-expanded",
+];
+
+/// Parses a css `font` shorthand like `italic small-caps bold condensed 16px/2 "Helvetica Neue", sans-serif`.
+///
+/// The weight is normalized to a number, and like the canvas the line height is checked but
+/// forced to `normal`. Returns `None` when the value is not a valid shorthand.
+pub fn parse_font(text: &str) -> Option<FontStyles> {
+    let mut style = None;
+    let mut variant = None;
+    let mut weight = None;
+    let mut stretch = None;
+    let mut rest = text;
+    // style, variant, weight and stretch come in any order before the size, `normal` resets one of them
+    for _ in 0..=4 {
+        let (token, after) = next_token(rest);
+        if let Some(size) = parse_size(token.split('/').next()?) {
+            let mut rest = after;
+            let line_height = match token.split_once('/') {
+                Some((_, "")) => Some(next_token(after)),
+                Some((_, line_height)) => Some((line_height, after)),
+                None => {
+                    let (next, after) = next_token(after);
+                    match next.strip_prefix('/') {
+                        Some("") => Some(next_token(after)),
+                        Some(line_height) => Some((line_height, after)),
+                        None => None,
+                    }
+                }
+            };
+            if let Some((line_height, after)) = line_height {
+                if line_height != "normal" && parse_number(line_height).is_none() && parse_size(line_height).is_none() {
+                    return None;
+                }
+                rest = after;
+            }
+            let family = serialize_families(rest)?;
+            return Some(FontStyles {
+                family,
+                size,
+                stretch: stretch.unwrap_or("normal").to_string(),
+                style: style.unwrap_or(FontStyle::Normal),
+                variant: variant.unwrap_or("normal").to_string(),
+                weight: weight.unwrap_or(400).to_string(),
+                line_height: 1.2,
+                ..FontStyles::default()
+            });
+        }
+        match token {
+            "normal" => {}
+            "italic" if style.is_none() => style = Some(FontStyle::Italic),
+            "oblique" if style.is_none() => style = Some(FontStyle::Oblique),
+            "small-caps" if variant.is_none() => variant = Some("small-caps"),
+            "bold" | "bolder" if weight.is_none() => weight = Some(700),
+            "lighter" if weight.is_none() => weight = Some(100),
+            _ if STRETCHES.contains(&token) && stretch.is_none() => stretch = Some(token),
+            _ => match token.parse::<u16>() {
+                Ok(value) if (1..=1000).contains(&value) && weight.is_none() => weight = Some(value),
+                _ => return None,
+            },
+        }
+        rest = after;
+    }
+    None
+}
+
+impl FontStyles {
+    /// Serializes the font like the canvas `font` getter, `normal` values and the line height are left out.
+    pub fn to_css(&self) -> String {
+        let mut parts = vec![];
+        match self.style {
+            FontStyle::Italic => parts.push("italic".to_string()),
+            FontStyle::Oblique => parts.push("oblique".to_string()),
+            FontStyle::Normal => {}
+        }
+        if !self.variant.is_empty() && self.variant != "normal" {
+            parts.push(self.variant.clone());
+        }
+        match self.weight.as_str() {
+            "" | "normal" | "400" => {}
+            "bold" => parts.push("700".to_string()),
+            weight => parts.push(weight.to_string()),
+        }
+        if !self.stretch.is_empty() && self.stretch != "normal" {
+            parts.push(self.stretch.clone());
+        }
+        parts.push(format!("{}px", self.size));
+        if let Some(family) = serialize_families(&self.family) {
+            parts.push(family);
+        }
+        parts.join(" ")
+    }
+}
+
+fn next_token(text: &str) -> (&str, &str) {
+    let text = text.trim_start();
+    let end = text.find(char::is_whitespace).unwrap_or(text.len());
+    (&text[..end], &text[end..])
+}
+
+fn parse_number(text: &str) -> Option<f32> {
+    text.parse::<f32>().ok().filter(|value| value.is_finite() && *value >= 0.0)
+}
+
+/// Parses a font size in pixels.
+fn parse_size(text: &str) -> Option<f32> {
+    let keyword = match text {
+        "xx-small" => Some(9.0),
+        "x-small" => Some(10.0),
+        "small" => Some(13.0),
+        "medium" => Some(16.0),
+        "large" => Some(18.0),
+        "x-large" => Some(24.0),
+        "xx-large" => Some(32.0),
+        "xxx-large" => Some(48.0),
+        "larger" => Some(BASE_FONT_SIZE * 1.2),
+        "smaller" => Some(BASE_FONT_SIZE / 1.2),
+        _ => None,
+    };
+    if keyword.is_some() {
+        return keyword;
+    }
+    let units: [(&str, f32); 5] = [("px", 1.0), ("pt", 4.0 / 3.0), ("rem", BASE_FONT_SIZE), ("em", BASE_FONT_SIZE), ("%", BASE_FONT_SIZE / 100.0)];
+    units.iter().find_map(|(unit, scale)| {
+        text.strip_suffix(unit).and_then(parse_number).map(|value| value * scale)
+    })
+}
+
+/// Checks and normalizes a font family list, names are trimmed and quoted names double quoted.
+fn serialize_families(text: &str) -> Option<String> {
+    let families = split_families(text)?.into_iter().map(|(name, quoted)| {
+        match quoted {
+            // a name holding a double quote can only be single quoted
+            true if name.contains('"') => Some(format!("'{}'", name)),
+            true => Some(format!("\"{}\"", name)),
+            false if name.is_empty() => None,
+            false => Some(name.split_whitespace().collect::<Vec<_>>().join(" ")),
+        }
+    }).collect::<Option<Vec<String>>>()?;
+    Some(families.join(", "))
+}
+
+/// Splits a css `font-family` list at the commas outside of quotes. Names are trimmed and come
+/// with whether they were quoted, quoted names without their quotes.
+///
+/// Returns `None` when a quote is left open or only quotes part of a name.
+pub fn split_families(text: &str) -> Option<Vec<(&str, bool)>> {
+    let mut families = vec![];
+    let mut quote = None;
+    let mut start = 0;
+    for (index, c) in text.char_indices() {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, ',') => {
+                families.push(family_name(&text[start..index])?);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    if quote.is_some() {
+        return None;
+    }
+    families.push(family_name(&text[start..])?);
+    Some(families)
+}
+
+fn family_name(name: &str) -> Option<(&str, bool)> {
+    let name = name.trim();
+    let quoted = name.strip_prefix('"').and_then(|name| name.strip_suffix('"')).filter(|name| !name.contains('"'))
+        .or_else(|| name.strip_prefix('\'').and_then(|name| name.strip_suffix('\'')).filter(|name| !name.contains('\'')));
+    match quoted {
+        Some(name) => Some((name, true)),
+        None if name.contains(['"', '\'']) => None,
+        None => Some((name, false)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::font::FontStyle;
+    use crate::font::shorthand::{parse_font, split_families};
+
+    #[test]
+    fn shorthand() {
+        let font = parse_font("italic small-caps bold condensed 16px/2 'Helvetica Neue', sans-serif").unwrap();
+        assert_eq!(font.style, FontStyle::Italic);
+        assert_eq!((font.variant.as_str(), font.weight.as_str(), font.stretch.as_str()), ("small-caps", "700", "condensed"));
+        assert_eq!(font.size, 16.0);
+        assert_eq!(font.family, "\"Helvetica Neue\", sans-serif");
+        assert_eq!(font.to_css(), "italic small-caps 700 condensed 16px \"Helvetica Neue\", sans-serif");
+
+        let font = parse_font("300 normal 12pt / normal serif").unwrap();
+        assert_eq!((font.weight.as_str(), font.size), ("300", 16.0));
+        assert_eq!(font.to_css(), "300 16px serif");
+        assert_eq!(parse_font("2em  Foo   Bar").unwrap().to_css(), "20px Foo Bar");
+        assert_eq!(parse_font("150% monospace").unwrap().size, 15.0);
+
+        assert!(parse_font("16px").is_none());
+        assert!(parse_font("bold serif").is_none());
+        assert!(parse_font("bold bold 16px serif").is_none());
+        assert!(parse_font("16px/x serif").is_none());
+        assert!(parse_font("normal normal normal normal normal 16px serif").is_none());
+        assert!(parse_font("16px 'serif").is_none());
+
+        // commas inside quotes are part of the name
+        assert_eq!(parse_font("16px 'Foo, Bar', serif").unwrap().family, "\"Foo, Bar\", serif");
+        assert_eq!(parse_font(r#"16px 'Foo"s'"#).unwrap().family, r#"'Foo"s'"#);
+        assert!(parse_font("16px Foo,, serif").is_none());
+    }
+
+    #[test]
+    fn families() {
+        assert_eq!(split_families(r#" "Foo, Bar" ,serif, 'A "B"',C D"#), Some(vec![("Foo, Bar", true), ("serif", false), (r#"A "B""#, true), ("C D", false)]));
+        assert_eq!(split_families(""), Some(vec![("", false)]));
+        assert!(split_families(r#""Foo"#).is_none());
+        assert!(split_families(r#""Foo" Bar"#).is_none());
+        assert!(split_families(r#"Foo"Bar""#).is_none());
+    }
+}
@@ -0,0 +1,288 @@
+use crate::f32x2;
+use crate::paint::color::ColorU8;
+
+/// Encoded image formats `Image::decode` can recognize.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    Gif,
+    WebP,
+}
+
+impl ImageFormat {
+    /// Guesses the format from the file signature.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(ImageFormat::Png)
+        } else if bytes.starts_with(&[0xff, 0xd8, 0xff]) {
+            Some(ImageFormat::Jpeg)
+        } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+            Some(ImageFormat::Gif)
+        } else if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+            Some(ImageFormat::WebP)
+        } else {
+            None
+        }
+    }
+}
+
+/// A decoded image, the source of `draw_image` and `create_pattern`.
+#[derive(Clone, Debug)]
+pub struct Image {
+    /// Premultiplied RGBA pixels.
+    data: Vec<u8>,
+    size: f32x2,
+}
+
+impl Image {
+    /// Creates an image from premultiplied RGBA pixels.
+    ///
+    /// Returns `None` when `data` doesn't match `size`.
+    pub fn new(data: Vec<u8>, size: f32x2) -> Option<Self> {
+        let (width, height) = (size.x() as usize, size.y() as usize);
+        if width == 0 || height == 0 || data.len() != width * height * 4 {
+            return None;
+        }
+        Some(Image { data, size })
+    }
+
+    /// Creates an image from straight alpha RGBA pixels, like `ImageData.data`.
+    pub fn from_rgba(data: &[u8], size: f32x2) -> Option<Self> {
+        let mut result = Vec::with_capacity(data.len());
+        for p in data.chunks_exact(4) {
+            let color = ColorU8::from_rgba(p[0], p[1], p[2], p[3]).premultiply();
+            result.extend_from_slice(&[color.red(), color.green(), color.blue(), color.alpha()]);
+        }
+        Image::new(result, size)
+    }
+
+    /// Decodes an encoded image file.
+    ///
+    /// Returns `None` when the format is unknown, its cargo feature is disabled or the data is broken.
+    /// Only the first frame of animated images is decoded.
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        match ImageFormat::from_bytes(bytes)? {
+            #[cfg(feature = "png")]
+            ImageFormat::Png => decode_png(bytes),
+            #[cfg(feature = "jpeg")]
+            ImageFormat::Jpeg => decode_jpeg(bytes),
+            #[cfg(feature = "gif")]
+            ImageFormat::Gif => decode_gif(bytes),
+            #[cfg(feature = "webp")]
+            ImageFormat::WebP => decode_webp(bytes),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn size(&self) -> f32x2 {
+        self.size
+    }
+
+    pub fn width(&self) -> u32 {
+        self.size.x() as u32
+    }
+
+    pub fn height(&self) -> u32 {
+        self.size.y() as u32
+    }
+}
+
+/// Straight alpha RGBA pixels, like the canvas `ImageData`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageData {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+impl ImageData {
+    /// Creates transparent black image data.
+    pub fn new(width: u32, height: u32) -> Option<Self> {
+        ImageData::from_data(vec![0; width as usize * height as usize * 4], width, height)
+    }
+
+    /// Returns `None` when `data` doesn't match the size.
+    pub fn from_data(data: Vec<u8>, width: u32, height: u32) -> Option<Self> {
+        if width == 0 || height == 0 || data.len() != width as usize * height as usize * 4 {
+            return None;
+        }
+        Some(ImageData { width, height, data })
+    }
+
+    pub fn to_image(&self) -> Option<Image> {
+        Image::from_rgba(&self.data, f32x2([self.width as f32, self.height as f32]))
+    }
+}
+
+/// Expands gray, gray alpha and rgb pixels to straight alpha rgba.
+#[cfg(any(feature = "png", feature = "jpeg", feature = "webp"))]
+fn expand_to_rgba(data: &[u8], channels: usize) -> Vec<u8> {
+    let mut result = Vec::with_capacity(data.len() / channels * 4);
+    for p in data.chunks_exact(channels) {
+        match channels {
+            1 => result.extend_from_slice(&[p[0], p[0], p[0], 255]),
+            2 => result.extend_from_slice(&[p[0], p[0], p[0], p[1]]),
+            3 => result.extend_from_slice(&[p[0], p[1], p[2], 255]),
+            _ => result.extend_from_slice(&p[0..4]),
+        }
+    }
+    result
+}
+
+#[cfg(feature = "png")]
+fn decode_png(bytes: &[u8]) -> Option<Image> {
+    let mut decoder = png::Decoder::new(bytes);
+    // palette and low bit depth images are expanded, 16 bits channels are stripped to 8 bits
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().ok()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).ok()?;
+    buf.truncate(info.buffer_size());
+    let channels = match info.color_type {
+        png::ColorType::Grayscale => 1,
+        png::ColorType::GrayscaleAlpha => 2,
+        png::ColorType::Rgb => 3,
+        png::ColorType::Rgba => 4,
+        png::ColorType::Indexed => return None,
+    };
+    Image::from_rgba(&expand_to_rgba(&buf, channels), f32x2([info.width as f32, info.height as f32]))
+}
+
+#[cfg(feature = "jpeg")]
+fn decode_jpeg(bytes: &[u8]) -> Option<Image> {
+    use jpeg_decoder::PixelFormat;
+
+    let mut decoder = jpeg_decoder::Decoder::new(bytes);
+    let pixels = decoder.decode().ok()?;
+    let info = decoder.info()?;
+    let rgba = match info.pixel_format {
+        PixelFormat::L8 => expand_to_rgba(&pixels, 1),
+        // big endian samples, keep the high byte
+        PixelFormat::L16 => pixels.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], 255]).collect(),
+        PixelFormat::RGB24 => expand_to_rgba(&pixels, 3),
+        PixelFormat::CMYK32 => pixels.chunks_exact(4).flat_map(|p| {
+            let k = 255 - p[3] as u32;
+            let channel = |c: u8| ((255 - c as u32) * k / 255) as u8;
+            [channel(p[0]), channel(p[1]), channel(p[2]), 255]
+        }).collect(),
+    };
+    Image::from_rgba(&rgba, f32x2([info.width as f32, info.height as f32]))
+}
+
+#[cfg(feature = "gif")]
+fn decode_gif(bytes: &[u8]) -> Option<Image> {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = options.read_info(bytes).ok()?;
+    let (width, height) = (decoder.width() as usize, decoder.height() as usize);
+    let frame = decoder.read_next_frame().ok()??;
+
+    // the first frame may only cover part of the logical screen, the rest stays transparent
+    let mut rgba = vec![0; width * height * 4];
+    let (left, top) = (frame.left as usize, frame.top as usize);
+    let frame_width = frame.width as usize;
+    for (row, line) in frame.buffer.chunks_exact(frame_width * 4).enumerate() {
+        let y = top + row;
+        if y >= height || left >= width {
+            break;
+        }
+        let len = frame_width.min(width - left) * 4;
+        let start = (y * width + left) * 4;
+        rgba[start..start + len].copy_from_slice(&line[..len]);
+    }
+    Image::from_rgba(&rgba, f32x2([width as f32, height as f32]))
+}
+
+#[cfg(feature = "webp")]
+fn decode_webp(bytes: &[u8]) -> Option<Image> {
+    let mut decoder = image_webp::WebPDecoder::new(std::io::Cursor::new(bytes)).ok()?;
+    let (width, height) = decoder.dimensions();
+    let mut buf = vec![0; decoder.output_buffer_size()?];
+    decoder.read_image(&mut buf).ok()?;
+    let channels = if decoder.has_alpha() { 4 } else { 3 };
+    Image::from_rgba(&expand_to_rgba(&buf, channels), f32x2([width as f32, height as f32]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::skia::Pixmap;
+
+    #[test]
+    fn format() {
+        assert_eq!(ImageFormat::from_bytes(b"GIF89a\x01\x00"), Some(ImageFormat::Gif));
+        assert_eq!(ImageFormat::from_bytes(b"RIFF\0\0\0\0WEBPVP8 "), Some(ImageFormat::WebP));
+        assert_eq!(ImageFormat::from_bytes(&[0xff, 0xd8, 0xff, 0xe0]), Some(ImageFormat::Jpeg));
+        assert_eq!(ImageFormat::from_bytes(b"<svg"), None);
+        assert!(Image::decode(b"<svg").is_none());
+    }
+
+    #[test]
+    fn from_rgba() {
+        let image = Image::from_rgba(&[255, 0, 0, 128, 0, 0, 255, 255], f32x2([2.0, 1.0])).unwrap();
+        assert_eq!(image.data(), &[128, 0, 0, 128, 0, 0, 255, 255]);
+        assert!(Image::from_rgba(&[0; 4], f32x2([2.0, 1.0])).is_none());
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn decode_png() {
+        let mut pixmap = Pixmap::new(2, 1).unwrap();
+        pixmap.data_mut().copy_from_slice(&[128, 0, 0, 128, 0, 0, 255, 255]);
+        let image = Image::decode(&pixmap.encode_png().unwrap()).unwrap();
+        assert_eq!((image.width(), image.height()), (2, 1));
+        assert_eq!(image.data(), pixmap.data());
+    }
+
+    #[cfg(feature = "gif")]
+    #[test]
+    fn decode_gif() {
+        let mut bytes = vec![];
+        {
+            let mut encoder = gif::Encoder::new(&mut bytes, 3, 2, &[255, 0, 0, 0, 0, 255]).unwrap();
+            let mut frame = gif::Frame::from_indexed_pixels(2, 1, &[0, 1], None);
+            frame.left = 1;
+            frame.top = 1;
+            encoder.write_frame(&frame).unwrap();
+        }
+        let image = Image::decode(&bytes).unwrap();
+        assert_eq!((image.width(), image.height()), (3, 2));
+        assert_eq!(&image.data()[0..4], &[0, 0, 0, 0]);
+        assert_eq!(&image.data()[16..24], &[255, 0, 0, 255, 0, 0, 255, 255]);
+    }
+
+    #[cfg(feature = "jpeg")]
+    #[test]
+    fn decode_jpeg() {
+        // two 8x8 blocks, red on the left and blue on the right
+        let image = Image::decode(include_bytes!("../test/red_blue.jpg")).unwrap();
+        assert_eq!((image.width(), image.height()), (16, 8));
+        let near = |index: usize, expected: [u8; 4]| {
+            let pixel = &image.data()[index * 4..index * 4 + 4];
+            pixel.iter().zip(expected).all(|(channel, expected)| channel.abs_diff(expected) <= 2)
+        };
+        assert!(near(0, [255, 0, 0, 255]));
+        assert!(near(8 * 16 - 1, [0, 0, 255, 255]));
+    }
+
+    #[cfg(feature = "webp")]
+    #[test]
+    fn decode_webp() {
+        let encode = |data: &[u8], color: image_webp::ColorType| {
+            let mut bytes = vec![];
+            image_webp::WebPEncoder::new(&mut bytes).encode(data, 2, 1, color).unwrap();
+            bytes
+        };
+        let image = Image::decode(&encode(&[255, 0, 0, 128, 0, 0, 255, 255], image_webp::ColorType::Rgba8)).unwrap();
+        assert_eq!((image.width(), image.height()), (2, 1));
+        assert_eq!(image.data(), &[128, 0, 0, 128, 0, 0, 255, 255]);
+        let image = Image::decode(&encode(&[255, 0, 0, 0, 0, 255], image_webp::ColorType::Rgb8)).unwrap();
+        assert_eq!(image.data(), &[255, 0, 0, 255, 0, 0, 255, 255]);
+    }
+}
@@ -13,10 +13,12 @@ pub mod style_bucket;
 pub mod operate;
 pub mod font;
 pub mod filter;
+pub mod image;
 mod helps;
 
 pub use tiny_skia_path::f32x2 as f32x2;
 pub use path::PathData;
+pub use image::Image;
 pub use sk_path::{LineCap, LineJoin};
 
 
@@ -122,6 +122,8 @@ pub struct VectorSegment {
     pub fill_rule: FillRule,
     pub fill: Option<Paint>,
     pub stroke: Option<Stroke>,
+    /// Smooths the pixels of pattern paints when they are scaled or rotated.
+    pub image_smoothing: bool,
     pub clip: Option<ClipMask>,
     pub clear_rect: Option<ClearRect>,
 }
@@ -133,6 +135,7 @@ impl Default for VectorSegment {
             fill_rule: FillRule::Nonzero,
             fill: None,
             stroke: None,
+            image_smoothing: false,
             clip: None,
             clear_rect: None,
         }
@@ -145,7 +148,10 @@ pub struct PixelSegment {
     pub blend_mode: BlendMode,
     pub size: f32x2,
     pub opacity: f32,
+    pub image_smoothing: bool,
     pub transform: Transform,
+    /// The (x, y, width, height) part of the pixels to draw, all of them when `None`.
+    pub src_rect: Option<(f32, f32, f32, f32)>,
     pub clip: Option<ClipMask>,
     pub clear_rect: Option<ClearRect>,
 }
@@ -157,7 +163,9 @@ impl PixelSegment {
             blend_mode: BlendMode::default(),
             size,
             opacity: 1.0,
+            image_smoothing: false,
             transform: Transform::default(),
+            src_rect: None,
             clip: None,
             clear_rect: None,
         }
@@ -289,6 +297,11 @@ impl<'a> VectorAppender<'a> {
         self
     }
 
+    pub fn set_image_smoothing(&'a mut self, image_smoothing: bool) -> &mut Self {
+        self.segment.image_smoothing = image_smoothing;
+        self
+    }
+
     pub fn set_clip(&'a mut self, clip: Option<ClipMask>) -> &mut Self {
         self.segment.clip = clip;
         self
@@ -323,8 +336,23 @@ impl<'a> PixelAppender<'a> {
         self
     }
 
-    pub fn set_clip(&'a mut self, clip: ClipMask) -> &mut Self {
-        self.segment.clip = Some(clip);
+    pub fn set_opacity(&'a mut self, opacity: f32) -> &mut Self {
+        self.segment.opacity = opacity;
+        self
+    }
+
+    pub fn set_image_smoothing(&'a mut self, image_smoothing: bool) -> &mut Self {
+        self.segment.image_smoothing = image_smoothing;
+        self
+    }
+
+    pub fn set_src_rect(&'a mut self, src_rect: (f32, f32, f32, f32)) -> &mut Self {
+        self.segment.src_rect = Some(src_rect);
+        self
+    }
+
+    pub fn set_clip(&'a mut self, clip: Option<ClipMask>) -> &mut Self {
+        self.segment.clip = clip;
         self
     }
 }
@@ -1,6 +1,8 @@
 #[derive(Clone, Debug)]
 pub enum BlendMode {
-    SourceOver
+    SourceOver,
+    /// Replaces the destination pixels, used by `put_image_data`.
+    Source,
 }
 
 impl Default for BlendMode {
@@ -2,6 +2,7 @@ use crate::paint::blend::BlendMode;
 use crate::paint::color::Color;
 use crate::paint::shader::Shader;
 use crate::PathData;
+use crate::transform::Transform;
 
 pub mod color;
 pub mod shader;
@@ -35,6 +36,18 @@ impl Paint {
         self.set_color(Color::from_rgba8(r, g, b, a))
     }
 
+    pub fn set_shader(&mut self, shader: impl Into<Shader>) {
+        self.shader = shader.into();
+    }
+
+    pub fn set_transform(&mut self, ts: &Transform) {
+        self.shader.set_transform(ts);
+    }
+
+    pub fn apply_opacity(&mut self, opacity: f32) {
+        self.shader.apply_opacity(opacity);
+    }
+
     pub fn is_solid_color(&self) -> bool {
         matches!(self.shader, Shader::SolidColor(_))
     }
@@ -52,6 +65,12 @@ impl Paint {
         paint.set_color_rgba8(r, g, b, a);
         paint
     }
+
+    pub fn from_shader(shader: impl Into<Shader>) -> Self {
+        let mut paint = Paint::default();
+        paint.set_shader(shader);
+        paint
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
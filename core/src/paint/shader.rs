@@ -1,4 +1,7 @@
+use std::f32::consts::PI;
+use crate::f32x2;
 use crate::paint::color::Color;
+use crate::transform::Transform;
 
 #[derive(Clone, Debug)]
 pub enum Shader {
@@ -8,20 +11,372 @@ pub enum Shader {
     LinearGradient(LinearGradient),
     /// A radial gradient shader.
     RadialGradient(RadialGradient),
+    /// A conic gradient shader.
+    ConicGradient(ConicGradient),
     /// A pattern shader.
-    Pattern,
+    Pattern(Pattern),
 }
 
-pub struct Pattern<'a> {
-    pub data: &'a [u8]
+impl Shader {
+    /// Sets the transform the shader coordinates are interpreted in.
+    ///
+    /// Solid colors have no coordinates, so this is a no-op for them.
+    pub fn set_transform(&mut self, ts: &Transform) {
+        match self {
+            Shader::LinearGradient(gradient) => gradient.transform = *ts,
+            Shader::RadialGradient(gradient) => gradient.transform = *ts,
+            Shader::ConicGradient(gradient) => gradient.transform = *ts,
+            Shader::Pattern(pattern) => pattern.transform = *ts,
+            Shader::SolidColor(_) => {}
+        }
+    }
+
+    /// Multiplies the alpha of every color the shader paints by `opacity`, like `globalAlpha`.
+    pub fn apply_opacity(&mut self, opacity: f32) {
+        let stops = match self {
+            Shader::SolidColor(color) => return color.apply_opacity(opacity),
+            Shader::LinearGradient(gradient) => &mut gradient.stops,
+            Shader::RadialGradient(gradient) => &mut gradient.stops,
+            Shader::ConicGradient(gradient) => &mut gradient.stops,
+            Shader::Pattern(pattern) => {
+                // premultiplied pixels scale in every channel
+                let opacity = opacity.clamp(0.0, 1.0);
+                pattern.data.iter_mut().for_each(|c| *c = (*c as f32 * opacity).round() as u8);
+                return;
+            }
+        };
+        stops.iter_mut().for_each(|stop| stop.color.apply_opacity(opacity));
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Repetition {
+    #[default]
+    Repeat,
+    RepeatX,
+    RepeatY,
+    NoRepeat,
+}
+
+impl Repetition {
+    pub fn repeat_x(&self) -> bool {
+        matches!(self, Repetition::Repeat | Repetition::RepeatX)
+    }
+
+    pub fn repeat_y(&self) -> bool {
+        matches!(self, Repetition::Repeat | Repetition::RepeatY)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    /// Premultiplied RGBA pixels.
+    pub data: Vec<u8>,
+    pub size: f32x2,
+    pub repetition: Repetition,
+    /// The pattern's own matrix, see `set_transform`.
+    pub pattern_transform: Transform,
+    /// The context transform at the time the pattern is used.
+    pub transform: Transform,
+}
+
+impl Pattern {
+    /// Creates a pattern from premultiplied RGBA pixels.
+    ///
+    /// Returns `None` when `data` doesn't match `size`.
+    pub fn new(data: &[u8], size: f32x2, repetition: Repetition) -> Option<Self> {
+        let (width, height) = (size.x() as usize, size.y() as usize);
+        if width == 0 || height == 0 || data.len() != width * height * 4 {
+            return None;
+        }
+        Some(Pattern {
+            data: data.to_vec(),
+            size,
+            repetition,
+            pattern_transform: Transform::default(),
+            transform: Transform::default(),
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.size.x() as u32
+    }
+
+    pub fn height(&self) -> u32 {
+        self.size.y() as u32
+    }
+
+    /// Sets the pattern matrix, like `CanvasPattern.setTransform`.
+    pub fn set_transform(&mut self, ts: &Transform) {
+        self.pattern_transform = *ts;
+    }
+
+    /// Returns the transform mapping pattern space to the device.
+    pub fn get_transform(&self) -> Transform {
+        let mut ts = self.transform;
+        ts.append(&self.pattern_transform);
+        ts
+    }
+
+    /// Returns the premultiplied pixel of the pattern at a point in pattern space,
+    /// taking repetition into account.
+    pub fn sample(&self, x: f32, y: f32) -> Option<[u8; 4]> {
+        let (width, height) = (self.size.x(), self.size.y());
+        let wrap = |v: f32, size: f32, repeat: bool| {
+            if repeat {
+                Some(v.rem_euclid(size))
+            } else if v >= 0.0 && v < size {
+                Some(v)
+            } else {
+                None
+            }
+        };
+        let x = wrap(x, width, self.repetition.repeat_x())? as usize;
+        let y = wrap(y, height, self.repetition.repeat_y())? as usize;
+        let index = (y.min(height as usize - 1) * width as usize + x.min(width as usize - 1)) * 4;
+        let pixel = self.data.get(index..index + 4)?;
+        Some([pixel[0], pixel[1], pixel[2], pixel[3]])
+    }
+
+    /// Like `sample`, but blends the four pixels around the point by their distance to it.
+    pub fn sample_bilinear(&self, x: f32, y: f32) -> Option<[u8; 4]> {
+        // pixel centers sit at half coordinates
+        let (x, y) = (x - 0.5, y - 0.5);
+        let (fx, fy) = (x - x.floor(), y - y.floor());
+        let (x, y) = (x.floor() + 0.5, y.floor() + 0.5);
+        let neighbours = [
+            (x, y, (1.0 - fx) * (1.0 - fy)),
+            (x + 1.0, y, fx * (1.0 - fy)),
+            (x, y + 1.0, (1.0 - fx) * fy),
+            (x + 1.0, y + 1.0, fx * fy),
+        ];
+        let mut sum = [0.0f32; 4];
+        let mut inside = false;
+        for (x, y, weight) in neighbours {
+            if let Some(pixel) = self.sample(x, y) {
+                inside = true;
+                for (sum, channel) in sum.iter_mut().zip(pixel) {
+                    *sum += channel as f32 * weight;
+                }
+            }
+        }
+        inside.then(|| sum.map(|channel| channel.round() as u8))
+    }
+}
+
+impl From<Pattern> for Shader {
+    fn from(pattern: Pattern) -> Self {
+        Shader::Pattern(pattern)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GradientStop {
+    pub offset: f32,
+    pub color: Color,
+}
+
+/// Inserts a stop keeping the list sorted by offset.
+///
+/// Stops with the same offset keep their insertion order, like the canvas api.
+fn insert_stop(stops: &mut Vec<GradientStop>, offset: f32, color: Color) -> Option<()> {
+    if !(0.0..=1.0).contains(&offset) {
+        return None;
+    }
+    let index = stops.iter().position(|stop| stop.offset > offset).unwrap_or(stops.len());
+    stops.insert(index, GradientStop { offset, color });
+    Some(())
+}
+
+/// Returns the color at `t`, values outside of the stops take the nearest stop color.
+pub fn interpolate_stops(stops: &[GradientStop], t: f32) -> Color {
+    let first = match stops.first() {
+        Some(stop) => stop,
+        None => return Color::TRANSPARENT,
+    };
+    if t <= first.offset {
+        return first.color;
+    }
+    for pair in stops.windows(2) {
+        let (start, end) = (&pair[0], &pair[1]);
+        if t < end.offset {
+            let ratio = (t - start.offset) / (end.offset - start.offset);
+            let mix = |a: f32, b: f32| a + (b - a) * ratio;
+            let mut color = start.color;
+            color.set_red(mix(start.color.red(), end.color.red()));
+            color.set_green(mix(start.color.green(), end.color.green()));
+            color.set_blue(mix(start.color.blue(), end.color.blue()));
+            color.set_alpha(mix(start.color.alpha(), end.color.alpha()));
+            return color;
+        }
+    }
+    stops.last().map(|stop| stop.color).unwrap_or(Color::TRANSPARENT)
 }
 
 #[derive(Clone, Debug)]
 pub struct LinearGradient {
+    pub x0: f32,
+    pub y0: f32,
+    pub x1: f32,
+    pub y1: f32,
+    pub stops: Vec<GradientStop>,
+    pub transform: Transform,
+}
+
+impl LinearGradient {
+    pub fn new(x0: f32, y0: f32, x1: f32, y1: f32) -> Self {
+        LinearGradient {
+            x0,
+            y0,
+            x1,
+            y1,
+            stops: vec![],
+            transform: Transform::default(),
+        }
+    }
+
+    /// Adds a color stop, `offset` must be in 0..=1 range.
+    pub fn add_color_stop(&mut self, offset: f32, color: Color) -> Option<()> {
+        insert_stop(&mut self.stops, offset, color)
+    }
+
+    /// A gradient without stops or with equal start and end points paints nothing.
+    pub fn is_degenerate(&self) -> bool {
+        self.stops.is_empty() || (self.x0 == self.x1 && self.y0 == self.y1)
+    }
+}
 
+impl From<LinearGradient> for Shader {
+    fn from(gradient: LinearGradient) -> Self {
+        Shader::LinearGradient(gradient)
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct RadialGradient {
+    pub x0: f32,
+    pub y0: f32,
+    pub r0: f32,
+    pub x1: f32,
+    pub y1: f32,
+    pub r1: f32,
+    pub stops: Vec<GradientStop>,
+    pub transform: Transform,
+}
+
+impl RadialGradient {
+    /// Creates a gradient between the start circle and the end circle.
+    ///
+    /// Returns `None` when one of the radii is negative.
+    pub fn new(x0: f32, y0: f32, r0: f32, x1: f32, y1: f32, r1: f32) -> Option<Self> {
+        if r0 < 0.0 || r1 < 0.0 {
+            return None;
+        }
+        Some(RadialGradient {
+            x0,
+            y0,
+            r0,
+            x1,
+            y1,
+            r1,
+            stops: vec![],
+            transform: Transform::default(),
+        })
+    }
 
-}
\ No newline at end of file
+    /// Adds a color stop, `offset` must be in 0..=1 range.
+    pub fn add_color_stop(&mut self, offset: f32, color: Color) -> Option<()> {
+        insert_stop(&mut self.stops, offset, color)
+    }
+
+    /// A gradient without stops or with two identical circles paints nothing.
+    pub fn is_degenerate(&self) -> bool {
+        self.stops.is_empty() || (self.x0 == self.x1 && self.y0 == self.y1 && self.r0 == self.r1)
+    }
+
+    /// Returns the gradient position of a point in gradient space.
+    ///
+    /// This is the largest `ω` whose circle `r(ω) = r0 + ω * (r1 - r0)` passes through the point
+    /// with a non-negative radius, or `None` when no such circle exists.
+    pub fn position(&self, x: f32, y: f32) -> Option<f32> {
+        let (cdx, cdy, dr) = (self.x1 - self.x0, self.y1 - self.y0, self.r1 - self.r0);
+        let (pdx, pdy) = (x - self.x0, y - self.y0);
+        let a = cdx * cdx + cdy * cdy - dr * dr;
+        let b = pdx * cdx + pdy * cdy + self.r0 * dr;
+        let c = pdx * pdx + pdy * pdy - self.r0 * self.r0;
+        let valid = |w: f32| self.r0 + w * dr >= 0.0;
+        if a.abs() < f32::EPSILON {
+            if b == 0.0 {
+                return None;
+            }
+            let w = c / (2.0 * b);
+            return if valid(w) { Some(w) } else { None };
+        }
+        let discriminant = b * b - a * c;
+        if discriminant < 0.0 {
+            return None;
+        }
+        let sqrt = discriminant.sqrt();
+        let (w1, w2) = ((b + sqrt) / a, (b - sqrt) / a);
+        let (max, min) = if w1 > w2 { (w1, w2) } else { (w2, w1) };
+        if valid(max) {
+            Some(max)
+        } else if valid(min) {
+            Some(min)
+        } else {
+            None
+        }
+    }
+}
+
+impl From<RadialGradient> for Shader {
+    fn from(gradient: RadialGradient) -> Self {
+        Shader::RadialGradient(gradient)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConicGradient {
+    /// Start angle in degrees, like `rotate` and `arc`.
+    pub angle: f32,
+    pub cx: f32,
+    pub cy: f32,
+    pub stops: Vec<GradientStop>,
+    pub transform: Transform,
+}
+
+impl ConicGradient {
+    pub fn new(angle: f32, cx: f32, cy: f32) -> Self {
+        ConicGradient {
+            angle,
+            cx,
+            cy,
+            stops: vec![],
+            transform: Transform::default(),
+        }
+    }
+
+    /// Adds a color stop, `offset` must be in 0..=1 range.
+    pub fn add_color_stop(&mut self, offset: f32, color: Color) -> Option<()> {
+        insert_stop(&mut self.stops, offset, color)
+    }
+
+    /// A gradient without stops paints nothing.
+    pub fn is_degenerate(&self) -> bool {
+        self.stops.is_empty()
+    }
+
+    /// Returns the gradient position of a point in gradient space, in 0..1 range.
+    ///
+    /// The position grows clockwise starting from `angle`.
+    pub fn position(&self, x: f32, y: f32) -> f32 {
+        let theta = (y - self.cy).atan2(x - self.cx) - self.angle.to_radians();
+        (theta / (2.0 * PI)).rem_euclid(1.0)
+    }
+}
+
+impl From<ConicGradient> for Shader {
+    fn from(gradient: ConicGradient) -> Self {
+        Shader::ConicGradient(gradient)
+    }
+}
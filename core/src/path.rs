@@ -146,6 +146,22 @@ impl PathData {
         }
     }
 
+    /// Adds an elliptical arc, angles are in degrees and grow clockwise from the x axis.
+    ///
+    /// Returns `None` when one of the radii is negative.
+    pub fn ellipse(&mut self, x: f32, y: f32, radius_x: f32, radius_y: f32, rotation: f32, start_angle: f32, end_angle: f32, anticlockwise: bool) -> Option<()> {
+        let ((sx, sy), curves) = describe_ellipse(x, y, radius_x, radius_y, rotation, start_angle, end_angle, anticlockwise)?;
+        if self.is_empty() {
+            self.move_to(sx, sy);
+        } else {
+            self.line_to(sx, sy);
+        }
+        for [x1, y1, x2, y2, x, y] in curves {
+            self.curve_to(x1, y1, x2, y2, x, y);
+        }
+        Some(())
+    }
+
     #[inline]
     pub fn arc_to(
         &mut self,
@@ -183,6 +199,21 @@ impl PathData {
         self.push(PathSegment::ClosePath);
     }
 
+    /// Returns the current point, which is the start of the subpath after a close.
+    ///
+    /// Returns `None` for an empty path.
+    pub fn current_pos(&self) -> Option<(f32, f32)> {
+        match self.last()? {
+            PathSegment::MoveTo { x, y }
+            | PathSegment::LineTo { x, y }
+            | PathSegment::CurveTo { x, y, .. } => Some((*x, *y)),
+            PathSegment::ClosePath => self.iter().rev().find_map(|seg| match seg {
+                PathSegment::MoveTo { x, y } => Some((*x, *y)),
+                _ => None,
+            }),
+        }
+    }
+
     #[inline]
     pub fn last_pos(&self) -> (f32, f32) {
         let seg = self.last().expect("path must not be empty").clone();
@@ -257,6 +288,104 @@ impl PathData {
         }
         Some(bbox)
     }
+
+    /// Returns the path with its outline pushed outwards by `strength`, like FreeType emboldening.
+    ///
+    /// Every point, control points included, moves along the bisector of its edge normals. Holes
+    /// wind the other way than the outer contours, so they shrink.
+    pub fn embolden(&self, strength: f32) -> PathData {
+        let mut points = vec![];
+        let mut contours = vec![];
+        for segment in self.iter() {
+            match *segment {
+                PathSegment::MoveTo { x, y } => {
+                    contours.push(points.len()..points.len());
+                    points.push((x, y));
+                }
+                PathSegment::LineTo { x, y } => points.push((x, y)),
+                PathSegment::CurveTo { x, y, x1, y1, x2, y2 } => points.extend_from_slice(&[(x1, y1), (x2, y2), (x, y)]),
+                PathSegment::ClosePath => {}
+            }
+            if let Some(contour) = contours.last_mut() {
+                contour.end = points.len();
+            }
+        }
+        let area: f32 = contours.iter().map(|contour| {
+            let contour = &points[contour.clone()];
+            (0..contour.len()).map(|i| {
+                let ((x1, y1), (x2, y2)) = (contour[i], contour[(i + 1) % contour.len()]);
+                x1 * y2 - x2 * y1
+            }).sum::<f32>()
+        }).sum();
+        // the outwards normal is on the right of the edges of a positive area outline
+        let sign = if area >= 0.0 { 1.0 } else { -1.0 };
+        let normal = |(x1, y1): (f32, f32), (x2, y2): (f32, f32)| {
+            let (dx, dy) = (x2 - x1, y2 - y1);
+            let len = (dx * dx + dy * dy).sqrt();
+            (sign * dy / len, -sign * dx / len)
+        };
+
+        let mut moved = points.clone();
+        for range in contours.iter() {
+            let contour = &points[range.clone()];
+            let len = contour.len();
+            for (i, point) in contour.iter().enumerate() {
+                let prev = (1..len).map(|k| contour[(i + len - k) % len]).find(|p| p != point);
+                let next = (1..len).map(|k| contour[(i + k) % len]).find(|p| p != point);
+                let (prev, next) = match (prev, next) {
+                    (Some(prev), Some(next)) => (prev, next),
+                    _ => continue,
+                };
+                let (n1, n2) = (normal(prev, *point), normal(*point, next));
+                let d = 1.0 + n1.0 * n2.0 + n1.1 * n2.1;
+                // the miter of an almost reversing turn shoots far away, leave the point there
+                if d < 0.0625 {
+                    continue;
+                }
+                moved[range.start + i] = (point.0 + (n1.0 + n2.0) * strength / d, point.1 + (n1.1 + n2.1) * strength / d);
+            }
+        }
+
+        let mut moved = moved.into_iter();
+        let mut path = PathData::new();
+        for segment in self.iter() {
+            match segment {
+                PathSegment::MoveTo { .. } => {
+                    let (x, y) = moved.next().unwrap();
+                    path.move_to(x, y);
+                }
+                PathSegment::LineTo { .. } => {
+                    let (x, y) = moved.next().unwrap();
+                    path.line_to(x, y);
+                }
+                PathSegment::CurveTo { .. } => {
+                    let ((x1, y1), (x2, y2), (x, y)) = (moved.next().unwrap(), moved.next().unwrap(), moved.next().unwrap());
+                    path.curve_to(x1, y1, x2, y2, x, y);
+                }
+                PathSegment::ClosePath => path.close(),
+            }
+        }
+        path
+    }
+}
+
+/// A `round_rect` corner radius, `x` and `y` differ for elliptical corners.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CornerRadius {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl From<f32> for CornerRadius {
+    fn from(radius: f32) -> Self {
+        CornerRadius { x: radius, y: radius }
+    }
+}
+
+impl From<(f32, f32)> for CornerRadius {
+    fn from((x, y): (f32, f32)) -> Self {
+        CornerRadius { x, y }
+    }
 }
 
 impl PathData {
@@ -272,6 +401,75 @@ impl PathData {
         }))
     }
 
+    /// Creates a rounded rect, `radii` lists 1 to 4 corner radii like the canvas `roundRect`.
+    ///
+    /// Returns `None` for an empty or too long radii list and for negative radii.
+    /// Radii that don't fit in the rect are scaled down together.
+    pub fn create_round_rect(x: f32, y: f32, w: f32, h: f32, radii: &[CornerRadius]) -> Option<PathData> {
+        if !(x.is_finite() && y.is_finite() && w.is_finite() && h.is_finite()) {
+            return None;
+        }
+        if radii.iter().any(|r| !(r.x >= 0.0 && r.y >= 0.0)) {
+            return None;
+        }
+        let (mut upper_left, mut upper_right, mut lower_right, mut lower_left) = match *radii {
+            [r] => (r, r, r, r),
+            [a, b] => (a, b, a, b),
+            [a, b, c] => (a, b, c, b),
+            [a, b, c, d] => (a, b, c, d),
+            _ => return None,
+        };
+        // negative sizes mirror the corners
+        let (x, w) = if w < 0.0 {
+            std::mem::swap(&mut upper_left, &mut upper_right);
+            std::mem::swap(&mut lower_left, &mut lower_right);
+            (x + w, -w)
+        } else {
+            (x, w)
+        };
+        let (y, h) = if h < 0.0 {
+            std::mem::swap(&mut upper_left, &mut lower_left);
+            std::mem::swap(&mut upper_right, &mut lower_right);
+            (y + h, -h)
+        } else {
+            (y, h)
+        };
+
+        let top = upper_left.x + upper_right.x;
+        let right = upper_right.y + lower_right.y;
+        let bottom = lower_right.x + lower_left.x;
+        let left = upper_left.y + lower_left.y;
+        let scale = [w / top, h / right, w / bottom, h / left].iter().fold(1.0f32, |scale, s| if s.is_nan() { scale } else { scale.min(*s) });
+        for corner in [&mut upper_left, &mut upper_right, &mut lower_right, &mut lower_left] {
+            corner.x *= scale;
+            corner.y *= scale;
+        }
+
+        let mut path = PathData::new();
+        let corner = |path: &mut PathData, cx: f32, cy: f32, r: CornerRadius, start_angle: f32| {
+            if r.x == 0.0 || r.y == 0.0 {
+                return;
+            }
+            if let Some((_, curves)) = describe_ellipse(cx, cy, r.x, r.y, 0.0, start_angle, start_angle + 90.0, false) {
+                for [x1, y1, x2, y2, x, y] in curves {
+                    path.curve_to(x1, y1, x2, y2, x, y);
+                }
+            }
+        };
+        path.move_to(x + upper_left.x, y);
+        path.line_to(x + w - upper_right.x, y);
+        corner(&mut path, x + w - upper_right.x, y + upper_right.y, upper_right, 270.0);
+        path.line_to(x + w, y + h - lower_right.y);
+        corner(&mut path, x + w - lower_right.x, y + h - lower_right.y, lower_right, 0.0);
+        path.line_to(x + lower_left.x, y + h);
+        corner(&mut path, x + lower_left.x, y + h - lower_left.y, lower_left, 90.0);
+        path.line_to(x, y + upper_left.y);
+        corner(&mut path, x + upper_left.x, y + upper_left.y, upper_left, 180.0);
+        path.close();
+        path.move_to(x, y);
+        Some(path)
+    }
+
     pub fn create_rect_reverse(x: f32, y: f32, w: f32, h: f32) -> Option<PathData> {
         Rect::from_xywh(x, y, w, h).and_then(|rect| Some({
             let mut path = PathData::new();
@@ -567,6 +765,48 @@ fn describe_arc(x: f32, y: f32, radius: f32, start_angle: f32, end_angle: f32) -
     (sx, sy, radius, 0.0, large_arc_flag, false, ex, ey)
 }
 
+/// Returns the start point of the ellipse arc and the cubic curves approximating it.
+///
+/// The sweep is split into parts of at most 90 degrees, each one becoming a single curve.
+fn describe_ellipse(x: f32, y: f32, radius_x: f32, radius_y: f32, rotation: f32, start_angle: f32, end_angle: f32, anticlockwise: bool) -> Option<((f32, f32), Vec<[f32; 6]>)> {
+    if radius_x < 0.0 || radius_y < 0.0 {
+        return None;
+    }
+    let sweep = if !anticlockwise && end_angle - start_angle >= 360.0 {
+        360.0
+    } else if anticlockwise && start_angle - end_angle >= 360.0 {
+        -360.0
+    } else if anticlockwise {
+        -(start_angle - end_angle).rem_euclid(360.0)
+    } else {
+        (end_angle - start_angle).rem_euclid(360.0)
+    };
+
+    let (sin_r, cos_r) = rotation.to_radians().sin_cos();
+    let point = |theta: f32| {
+        let (sin, cos) = theta.sin_cos();
+        let (px, py) = (radius_x * cos, radius_y * sin);
+        (x + px * cos_r - py * sin_r, y + px * sin_r + py * cos_r)
+    };
+    let tangent = |theta: f32| {
+        let (sin, cos) = theta.sin_cos();
+        let (dx, dy) = (-radius_x * sin, radius_y * cos);
+        (dx * cos_r - dy * sin_r, dx * sin_r + dy * cos_r)
+    };
+
+    let start = start_angle.to_radians();
+    let count = (sweep.abs() / 90.0).ceil() as usize;
+    let step = sweep.to_radians() / count.max(1) as f32;
+    let k = 4.0 / 3.0 * (step / 4.0).tan();
+    let curves = (0..count).map(|i| {
+        let (a0, a1) = (start + step * i as f32, start + step * (i + 1) as f32);
+        let ((x0, y0), (x, y)) = (point(a0), point(a1));
+        let ((dx0, dy0), (dx1, dy1)) = (tangent(a0), tangent(a1));
+        [x0 + k * dx0, y0 + k * dy0, x - k * dx1, y - k * dy1, x, y]
+    }).collect();
+    Some((point(start), curves))
+}
+
 impl PathBuilder {
     pub fn arc(&mut self, x: f32, y: f32, radius: f32, start_angle: f32, end_angle: f32, anticlockwise: bool, transform: &Transform) {
         let (sx, sy, radius, rotation, large_arc, _sweep_flag, x, y) = describe_arc(x, y, radius, start_angle, end_angle);
@@ -594,8 +834,56 @@ impl PathBuilder {
             }
         }
     }
-    pub fn arc_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, transform: &Transform) {
-        todo!()
+    /// Adds a line to the first tangent point and an arc of `radius` tangent to the lines
+    /// from the current point to (x1, y1) and from (x1, y1) to (x2, y2).
+    ///
+    /// Returns `None` when the radius is negative.
+    pub fn arc_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, radius: f32, transform: &Transform) -> Option<()> {
+        if radius < 0.0 {
+            return None;
+        }
+        let (x0, y0) = match self.0.current_pos() {
+            Some((x, y)) => match transform.invert() {
+                Some(inverse) => inverse.apply(x, y),
+                None => {
+                    self.line_to(x1, y1, transform);
+                    return Some(());
+                }
+            },
+            None => {
+                self.move_to(x1, y1, transform);
+                return Some(());
+            }
+        };
+
+        let (v1x, v1y) = (x0 - x1, y0 - y1);
+        let (v2x, v2y) = (x2 - x1, y2 - y1);
+        let (len1, len2) = ((v1x * v1x + v1y * v1y).sqrt(), (v2x * v2x + v2y * v2y).sqrt());
+        let cross = v1x * v2y - v1y * v2x;
+        if len1 == 0.0 || len2 == 0.0 || radius == 0.0 || cross.abs() <= f32::EPSILON * len1 * len2 {
+            self.line_to(x1, y1, transform);
+            return Some(());
+        }
+
+        let (v1x, v1y, v2x, v2y) = (v1x / len1, v1y / len1, v2x / len2, v2y / len2);
+        // half of the angle between the two lines at (x1, y1)
+        let half = (v1x * v2x + v1y * v2y).clamp(-1.0, 1.0).acos() / 2.0;
+        let tangent = radius / half.tan();
+        let (t1x, t1y) = (x1 + v1x * tangent, y1 + v1y * tangent);
+        let (t2x, t2y) = (x1 + v2x * tangent, y1 + v2y * tangent);
+        let (bx, by) = (v1x + v2x, v1y + v2y);
+        let bisector = (bx * bx + by * by).sqrt();
+        let distance = radius / half.sin();
+        let (cx, cy) = (x1 + bx / bisector * distance, y1 + by / bisector * distance);
+
+        let start_angle = (t1y - cy).atan2(t1x - cx).to_degrees();
+        let end_angle = (t2y - cy).atan2(t2x - cx).to_degrees();
+        self.line_to(t1x, t1y, transform);
+        let (_, curves) = describe_ellipse(cx, cy, radius, radius, 0.0, start_angle, end_angle, cross > 0.0)?;
+        for [x1, y1, x2, y2, x, y] in curves {
+            self.bezier_curve_to(x1, y1, x2, y2, x, y, transform);
+        }
+        Some(())
     }
     pub fn begin_path(&mut self) {
         self.0.0.clear();
@@ -609,13 +897,28 @@ impl PathBuilder {
     pub fn close_path(&mut self) {
         self.0.close();
     }
-    pub fn ellipse(&mut self) {
-        todo!()
+    pub fn ellipse(&mut self, x: f32, y: f32, radius_x: f32, radius_y: f32, rotation: f32, start_angle: f32, end_angle: f32, anticlockwise: bool, transform: &Transform) -> Option<()> {
+        let ((sx, sy), curves) = describe_ellipse(x, y, radius_x, radius_y, rotation, start_angle, end_angle, anticlockwise)?;
+        if self.0.is_empty() {
+            self.move_to(sx, sy, transform);
+        } else {
+            self.line_to(sx, sy, transform);
+        }
+        for [x1, y1, x2, y2, x, y] in curves {
+            self.bezier_curve_to(x1, y1, x2, y2, x, y, transform);
+        }
+        Some(())
     }
     pub fn line_to(&mut self, x: f32, y: f32, transform: &Transform) {
         let (x, y) = transform.apply(x, y);
         self.0.line_to(x, y)
     }
+    pub fn round_rect(&mut self, x: f32, y: f32, width: f32, height: f32, radii: &[CornerRadius], transform: &Transform) -> Option<()> {
+        let mut path = PathData::create_round_rect(x, y, width, height, radii)?;
+        path.transform(*transform);
+        self.append_path(&mut path);
+        Some(())
+    }
     pub fn move_to(&mut self, x: f32, y: f32, transform: &Transform) {
         let (x, y) = transform.apply(x, y);
         self.0.move_to(x, y)
@@ -1,7 +1,7 @@
 use std::fmt::{Display, Formatter};
 use std::ops::{Deref, DerefMut};
 use tiny_skia_path::{LineCap, LineJoin, StrokeDash};
-use crate::font::FontStyles;
+use crate::font::{FaceSelector, FontFeature, FontKerning, FontStyle, FontStyles, FontVariation, TextMetrics};
 use crate::paint::blend::BlendMode;
 use crate::paint::color::Color;
 use crate::paint::{ClipMask, FillRule, Paint};
@@ -29,10 +29,13 @@ pub enum TextBaseLine {
     Bottom,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
 pub enum TextDirection {
     Ltr,
     Rtl,
+    /// The direction of the canvas element, a context without one lays out as `Ltr`.
+    #[default]
+    Inherit,
 }
 
 #[derive(Clone, Debug)]
@@ -51,11 +54,19 @@ pub struct StyleBucket {
     pub shadow_color: Color,
     pub font_family: String,
     pub font_size: f32,
-    pub font_style: String,
+    pub font_style: FontStyle,
+    pub font_variant: String,
+    pub font_weight: String,
+    pub font_stretch: String,
+    pub font_kerning: FontKerning,
+    pub font_feature_settings: Vec<FontFeature>,
+    pub font_variation_settings: Vec<FontVariation>,
+    pub font_face: FaceSelector,
     pub text_align: TextAlign,
     pub text_base_line: TextBaseLine,
     pub direction: TextDirection,
     pub image_smoothing_enabled: bool,
+    pub global_alpha: f32,
     pub global_composite_operation: BlendMode,
     pub clip_mask: Option<ClipMask>,
 }
@@ -77,11 +88,19 @@ impl Default for StyleBucket {
             shadow_color: Color::from_rgba8(0, 0, 0, 0),
             font_family: "".to_string(),
             font_size: 16.0,
-            font_style: "".to_string(),
-            text_align: TextAlign::Left,
+            font_style: FontStyle::Normal,
+            font_variant: "normal".to_string(),
+            font_weight: "400".to_string(),
+            font_stretch: "normal".to_string(),
+            font_kerning: FontKerning::Auto,
+            font_feature_settings: vec![],
+            font_variation_settings: vec![],
+            font_face: FaceSelector::Family,
+            text_align: TextAlign::Start,
             text_base_line: TextBaseLine::Alphabetic,
-            direction: TextDirection::Ltr,
+            direction: TextDirection::Inherit,
             image_smoothing_enabled: false,
+            global_alpha: 1.0,
             global_composite_operation: BlendMode::default(),
             clip_mask: None,
         }
@@ -100,17 +119,27 @@ impl StyleBucket {
 
 impl StyleBucket {
     pub fn get_fill(&self) -> Paint {
-        self.fill.clone()
+        let mut fill = self.fill.clone();
+        fill.set_transform(&self.transform);
+        if self.global_alpha < 1.0 {
+            fill.apply_opacity(self.global_alpha);
+        }
+        fill
     }
 
     pub fn get_stroke(&self) -> Stroke {
+        let mut paint = self.stroke.clone();
+        paint.set_transform(&self.transform);
+        if self.global_alpha < 1.0 {
+            paint.apply_opacity(self.global_alpha);
+        }
         Stroke {
             width: self.line_width,
             miter_limit: self.miter_limit,
             line_cap: self.line_cap,
             line_join: self.line_join,
             dash: self.line_dash_offset.clone(),
-            paint: self.stroke.clone(),
+            paint,
         }
     }
 
@@ -119,9 +148,44 @@ impl StyleBucket {
         fs.size = self.font_size;
         fs.line_height = 1.2;
         fs.family = self.font_family.clone();
+        fs.style = self.font_style;
+        fs.variant = self.font_variant.clone();
+        fs.weight = self.font_weight.clone();
+        fs.stretch = self.font_stretch.clone();
+        fs.kerning = self.font_kerning;
+        fs.features = self.font_feature_settings.clone();
+        fs.variations = self.font_variation_settings.clone();
+        fs.face = self.font_face.clone();
+        fs.direction = self.direction;
         fs
     }
 
+    /// Returns the offset from the anchor point of `fill_text` to the text start on the alphabetic baseline.
+    ///
+    /// `start` and `end` alignments follow `direction`, `justify` is not a canvas value and is treated as `start`.
+    pub fn get_text_offset(&self, metrics: &TextMetrics) -> (f32, f32) {
+        let rtl = matches!(self.direction, TextDirection::Rtl);
+        let dx = match self.text_align {
+            TextAlign::Left => 0.0,
+            TextAlign::Right => -metrics.width,
+            TextAlign::Center => -metrics.width / 2.0,
+            TextAlign::Start | TextAlign::Justify if rtl => -metrics.width,
+            TextAlign::Start | TextAlign::Justify => 0.0,
+            TextAlign::End if rtl => 0.0,
+            TextAlign::End => -metrics.width,
+        };
+        // moving the anchor onto a baseline `b` above the alphabetic one pushes the text down by `b`
+        let dy = match self.text_base_line {
+            TextBaseLine::Top => metrics.em_height_ascent,
+            TextBaseLine::Hanging => metrics.hanging_baseline,
+            TextBaseLine::Middle => (metrics.em_height_ascent - metrics.em_height_descent) / 2.0,
+            TextBaseLine::Alphabetic => metrics.alphabetic_baseline,
+            TextBaseLine::Ideographic => metrics.ideographic_baseline,
+            TextBaseLine::Bottom => -metrics.em_height_descent,
+        };
+        (dx, dy)
+    }
+
     pub fn set_clip(&mut self, path: PathData, fill_rule: Option<FillRule>) {
         let clip_mask = ClipMask {
             path,
@@ -129,10 +193,6 @@ impl StyleBucket {
         };
         self.clip_mask = Some(clip_mask)
     }
-
-    pub fn get_font(&self) {
-        todo!()
-    }
 }
 
 #[derive(Clone, Debug, Default)]
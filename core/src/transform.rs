@@ -112,6 +112,23 @@ impl Transform {
         self.f = ts.f;
     }
 
+    /// Returns the inverted transform, `None` when it is not invertible.
+    pub fn invert(&self) -> Option<Transform> {
+        let det = self.a * self.d - self.b * self.c;
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+        let inv = 1.0 / det;
+        Some(Transform {
+            a: self.d * inv,
+            b: -self.b * inv,
+            c: -self.c * inv,
+            d: self.a * inv,
+            e: (self.c * self.f - self.d * self.e) * inv,
+            f: (self.b * self.e - self.a * self.f) * inv,
+        })
+    }
+
     /// Returns transform's translate part.
     pub fn get_translate(&self) -> (f32, f32) {
         (self.e, self.f)
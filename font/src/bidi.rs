@@ -0,0 +1,86 @@
+use unicode_bidi::{BidiInfo, Level};
+use painter_core::style_bucket::TextDirection;
+
+/// Splits the text into runs of a single direction with the Unicode bidi algorithm (UAX #9).
+///
+/// Runs come in visual order, from left to right, along with whether they are right to left.
+/// Paragraph separators are laid out on the same line, like the canvas does with newlines.
+pub(crate) fn visual_runs(text: &str, direction: TextDirection) -> Vec<(&str, bool)> {
+    let level = match direction {
+        TextDirection::Rtl => Level::rtl(),
+        TextDirection::Ltr | TextDirection::Inherit => Level::ltr(),
+    };
+    // the canvas replaces newlines with spaces, so the text is a single paragraph. A separator
+    // becomes a space per byte for the bidi analysis, the runs still slice the original text
+    let mut line = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\n' | '\r' | '\u{1C}'..='\u{1E}' | '\u{85}' | '\u{2029}' => line.extend(std::iter::repeat_n(' ', c.len_utf8())),
+            c => line.push(c),
+        }
+    }
+    let bidi = BidiInfo::new(&line, Some(level));
+    let mut result = vec![];
+    for paragraph in bidi.paragraphs.iter() {
+        let (levels, runs) = bidi.visual_runs(paragraph, paragraph.range.clone());
+        for run in runs {
+            let rtl = levels[run.start].is_rtl();
+            result.push((&text[run], rtl));
+        }
+    }
+    result
+}
+
+/// Reverses the text by clusters of a char with the marks and the zero width joiners following it,
+/// so combining marks stay after their base when a right to left run is laid out without shaping.
+#[cfg(not(feature = "shaping"))]
+pub(crate) fn reverse_clusters(text: &str) -> String {
+    use unicode_properties::{GeneralCategoryGroup, UnicodeGeneralCategory};
+
+    let mut clusters = vec![];
+    let mut start = 0;
+    let mut joined = false;
+    for (index, c) in text.char_indices() {
+        let extends = joined || c == '\u{200D}' || c.general_category_group() == GeneralCategoryGroup::Mark;
+        if index > 0 && !extends {
+            clusters.push(&text[start..index]);
+            start = index;
+        }
+        joined = c == '\u{200D}';
+    }
+    if start < text.len() {
+        clusters.push(&text[start..]);
+    }
+    clusters.into_iter().rev().collect()
+}
+
+#[cfg(test)]
+mod test {
+    use painter_core::style_bucket::TextDirection;
+    use crate::bidi::visual_runs;
+
+    #[test]
+    fn runs() {
+        assert_eq!(visual_runs("abc", TextDirection::Inherit), vec![("abc", false)]);
+        assert_eq!(visual_runs("abc אבג def", TextDirection::Ltr), vec![("abc ", false), ("אבג", true), (" def", false)]);
+        assert_eq!(visual_runs("abc אבג def", TextDirection::Rtl), vec![("def", false), (" אבג ", true), ("abc", false)]);
+        assert_eq!(visual_runs("שלום 123", TextDirection::Rtl), vec![("123", false), ("שלום ", true)]);
+        assert!(visual_runs("", TextDirection::Rtl).is_empty());
+        // newlines don't split the text into paragraphs
+        assert_eq!(visual_runs("abc\nאבג", TextDirection::Rtl), vec![("\nאבג", true), ("abc", false)]);
+        assert_eq!(visual_runs("אבג\u{2029}abc", TextDirection::Ltr), vec![("אבג", true), ("\u{2029}abc", false)]);
+    }
+
+    #[cfg(not(feature = "shaping"))]
+    #[test]
+    fn clusters() {
+        use crate::bidi::reverse_clusters;
+
+        assert_eq!(reverse_clusters("abc"), "cba");
+        // the marks of שָׁלוֹם stay after their base letters
+        assert_eq!(reverse_clusters("ש\u{05C1}\u{05B8}לו\u{05B9}ם"), "םו\u{05B9}לש\u{05C1}\u{05B8}");
+        assert_eq!(reverse_clusters("a\u{200D}bc"), "ca\u{200D}b");
+        assert_eq!(reverse_clusters("\u{0301}a"), "a\u{0301}");
+        assert_eq!(reverse_clusters(""), "");
+    }
+}
@@ -3,16 +3,26 @@ extern crate fontdb;
 extern crate painter_core;
 
 pub mod woff;
+pub mod woff2;
 pub mod parser;
+mod bidi;
+#[cfg(feature = "shaping")]
+mod shaping;
 
-pub use parser::{Glyph, GlyphExt};
+pub use parser::{Glyph, GlyphExt, parse_families};
 pub use fontdb::*;
-use painter_core::font::{FontBucket, FontStyles};
+use painter_core::font::{ColorGlyph, FaceId, FaceSelector, FontBucket, FontLoadError, LoadedFace, FontStyles, FontVariation, PositionedGlyph, TextMetrics};
 use painter_core::path::BoundingBox;
 use painter_core::PathData;
 use painter_core::transform::Transform;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
 
 pub mod check {
+    use painter_core::font::FontLoadError;
+
     //
     const SFNT_VERSION_TRUE_TYPE1: u32 = 0x00010000;
     // true
@@ -23,29 +33,42 @@ pub mod check {
     const SFNT_VERSION_OPEN_TYPE: u32 = 0x4F54544F;
     // wOFF
     const SFNT_VERSION_WOFF: u32 = 0x774f4646;
+    // wOF2
+    const SFNT_VERSION_WOFF2: u32 = 0x774f4632;
+    // ttcf
+    const COLLECTION_TAG: u32 = 0x74746366;
 
     fn get_data(data: &[u8], offset: usize) -> Option<u32> {
         let r = data.get(offset..offset + 4)?;
         Some(u32::from_be_bytes([r[0], r[1], r[2], r[3]]))
     }
 
-    pub fn check_type(data: &[u8]) -> Option<(String, bool)> {
-        let signature = get_data(data, 0)?;
+    pub fn check_type(data: &[u8]) -> Result<(String, bool), FontLoadError> {
+        let signature = get_data(data, 0).ok_or(FontLoadError::TruncatedHeader)?;
         if signature == SFNT_VERSION_TRUE_TYPE1 || signature == SFNT_VERSION_TRUE_TYPE2 || signature == SFNT_VERSION_TRUE_TYPE3 {
-            Some(("ttf".to_string(), false))
+            Ok(("ttf".to_string(), false))
         } else if signature == SFNT_VERSION_OPEN_TYPE {
-            Some(("otf".to_string(), false))
-        } else if signature == SFNT_VERSION_WOFF {
-            let tag = get_data(data, 4)?;
+            Ok(("otf".to_string(), false))
+        } else if signature == COLLECTION_TAG {
+            // a collection of OpenType faces is an otc, told apart by its first face
+            let offset = get_data(data, 12).ok_or(FontLoadError::TruncatedHeader)?;
+            let first = get_data(data, offset as usize).ok_or(FontLoadError::TruncatedHeader)?;
+            if first == SFNT_VERSION_OPEN_TYPE {
+                Ok(("otc".to_string(), false))
+            } else {
+                Ok(("ttc".to_string(), false))
+            }
+        } else if signature == SFNT_VERSION_WOFF || signature == SFNT_VERSION_WOFF2 {
+            let tag = get_data(data, 4).ok_or(FontLoadError::TruncatedHeader)?;
             if tag == SFNT_VERSION_TRUE_TYPE1 {
-                Some(("ttf".to_string(), true))
+                Ok(("ttf".to_string(), true))
             } else if tag == SFNT_VERSION_OPEN_TYPE {
-                Some(("otf".to_string(), true))
+                Ok(("otf".to_string(), true))
             } else {
-                None
+                Err(FontLoadError::UnsupportedFlavor)
             }
         } else {
-            None
+            Err(FontLoadError::BadSignature)
         }
     }
 }
@@ -53,61 +76,1074 @@ pub mod check {
 #[derive(Clone, Debug)]
 pub struct FontDB {
     db: Database,
+    /// The faces each loaded buffer added, in load order.
+    buffers: Vec<Vec<ID>>,
+    /// Every face ever loaded, a `FaceId` is the position in it.
+    ids: Vec<ID>,
+    /// The char ranges of the faces `itemize` looked at, so each face is parsed once.
+    coverage: RefCell<HashMap<ID, CharRanges>>,
+    /// The resolved fallback chains, cleared when the faces or the generic families change.
+    chains: RefCell<HashMap<ChainKey, Rc<[ID]>>>,
 }
 
+/// Sorted inclusive char ranges of a face.
+type CharRanges = Rc<[(u32, u32)]>;
+
+/// The family list, weight, style, stretch and face selector a fallback chain is resolved for.
+type ChainKey = (String, Weight, Style, Stretch, FaceSelector);
+
 impl FontDB {
     pub fn new() -> Self {
         FontDB {
-            db: Database::new()
+            db: Database::new(),
+            buffers: vec![],
+            ids: vec![],
+            coverage: RefCell::new(HashMap::new()),
+            chains: RefCell::new(HashMap::new()),
         }
     }
 
-    pub fn load_font(&mut self, buf: &[u8]) -> Option<()> {
+    /// Loads a TrueType, OpenType, WOFF or WOFF2 font, or every face of a `.ttc`/`.otc` collection,
+    /// returning the faces it added or why it is rejected.
+    pub fn load_font(&mut self, buf: &[u8]) -> Result<Vec<FaceId>, FontLoadError> {
         let (_, need_decompress) = check::check_type(buf)?;
-        let buf = if need_decompress {
-            woff::decompress_woff(buf)?
-        } else {
+        let buf = if !need_decompress {
             buf.to_vec()
+        } else if buf.starts_with(b"wOF2") {
+            woff2::decompress_woff2(buf)?
+        } else {
+            woff::decompress_woff(buf)?
         };
+        // fontdb skips the data silently when it is not a font
+        let count = self.db.len();
         self.db.load_font_data(buf);
-        Some(())
+        if self.db.len() == count {
+            return Err(FontLoadError::MalformedFont);
+        }
+        Ok(self.record_faces(count))
+    }
+
+    /// Loads a font file like `load_font`, TrueType and OpenType files are memory mapped instead of copied.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_font_file<P: AsRef<std::path::Path>>(&mut self, path: P) -> Result<Vec<FaceId>, FontLoadError> {
+        use std::io::Read;
+
+        let path = path.as_ref();
+        let mut signature = [0; 4];
+        std::fs::File::open(path)
+            .and_then(|mut file| file.read_exact(&mut signature))
+            .map_err(|err| FontLoadError::Io(err.kind()))?;
+        match &signature {
+            b"wOFF" | b"wOF2" => {
+                let buf = std::fs::read(path).map_err(|err| FontLoadError::Io(err.kind()))?;
+                self.load_font(&buf)
+            }
+            b"\0\x01\0\0" | b"true" | b"typ1" | b"OTTO" | b"ttcf" => {
+                let count = self.db.len();
+                self.db.load_font_file(path).map_err(|err| FontLoadError::Io(err.kind()))?;
+                if self.db.len() == count {
+                    return Err(FontLoadError::MalformedFont);
+                }
+                Ok(self.record_faces(count))
+            }
+            _ => Err(FontLoadError::BadSignature),
+        }
     }
 
+    /// Loads the font files of a directory and its subdirectories, skipping files that fail to load.
+    /// Symlinked files are followed, symlinked directories are not since they can loop back to a parent.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_fonts_dir<P: AsRef<std::path::Path>>(&mut self, path: P) -> Vec<FaceId> {
+        let mut ids = vec![];
+        let entries = match std::fs::read_dir(path) {
+            Ok(entries) => entries,
+            Err(_) => return ids,
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            // the entry type is the link itself, `path.is_dir` follows it
+            let is_link = entry.file_type().map_or(true, |file_type| file_type.is_symlink());
+            if path.is_dir() {
+                if !is_link {
+                    ids.extend(self.load_fonts_dir(&path));
+                }
+                continue;
+            }
+            let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_ascii_lowercase();
+            if matches!(extension.as_str(), "ttf" | "otf" | "ttc" | "otc" | "woff" | "woff2") {
+                ids.extend(self.load_font_file(&path).unwrap_or_default());
+            }
+        }
+        ids
+    }
+
+    /// Loads the fonts installed in the system font directories of Windows, macOS and Linux.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_system_fonts(&mut self) -> Vec<FaceId> {
+        let count = self.db.len();
+        self.db.load_system_fonts();
+        self.record_faces(count)
+    }
+
+    /// Sets the family a css generic family name like `serif` resolves to,
+    /// `false` when `generic` is not one of `serif`, `sans-serif`, `monospace`, `cursive` and `fantasy`.
+    pub fn set_generic_family(&mut self, generic: &str, family: &str) -> bool {
+        match generic {
+            "serif" => self.db.set_serif_family(family),
+            "sans-serif" => self.db.set_sans_serif_family(family),
+            "monospace" => self.db.set_monospace_family(family),
+            "cursive" => self.db.set_cursive_family(family),
+            "fantasy" => self.db.set_fantasy_family(family),
+            _ => return false,
+        }
+        self.chains.get_mut().clear();
+        true
+    }
+
+    /// Gives ids to the faces loaded after the first `count`, grouped by the file or buffer they came from.
+    fn record_faces(&mut self, count: usize) -> Vec<FaceId> {
+        self.chains.get_mut().clear();
+        let first = self.ids.len() as u32;
+        for face in self.db.faces()[count..].iter() {
+            // the faces of a collection come in index order, so a new file starts at index 0
+            match self.buffers.last_mut() {
+                Some(ids) if face.index != 0 && self.ids.len() as u32 > first => ids.push(face.id),
+                _ => self.buffers.push(vec![face.id]),
+            }
+            self.ids.push(face.id);
+        }
+        (first..self.ids.len() as u32).map(FaceId).collect()
+    }
+
+    /// Lists the loaded faces in load order.
+    pub fn faces(&self) -> Vec<LoadedFace> {
+        self.ids.iter().enumerate().filter_map(|(index, id)| {
+            let face = self.db.face(*id)?;
+            Some(LoadedFace {
+                id: FaceId(index as u32),
+                family: face.family.clone(),
+                post_script_name: face.post_script_name.clone(),
+                weight: face.weight.0,
+                style: match face.style {
+                    Style::Normal => painter_core::font::FontStyle::Normal,
+                    Style::Italic => painter_core::font::FontStyle::Italic,
+                    Style::Oblique => painter_core::font::FontStyle::Oblique,
+                },
+                stretch: stretch_keyword(face.stretch).to_string(),
+                monospaced: face.monospaced,
+                glyph_count: self.db.glyph_count(*id),
+                unicode_ranges: self.char_ranges(*id).to_vec(),
+            })
+        }).collect()
+    }
+
+    /// Unloads a face, `false` when it is not loaded. The other faces of its collection stay.
+    pub fn remove_face(&mut self, id: FaceId) -> bool {
+        let id = match self.ids.get(id.0 as usize) {
+            Some(id) => *id,
+            None => return false,
+        };
+        for ids in self.buffers.iter_mut() {
+            ids.retain(|face| *face != id);
+        }
+        self.buffers.retain(|ids| !ids.is_empty());
+        self.coverage.get_mut().remove(&id);
+        self.chains.get_mut().clear();
+        self.db.remove_face(id)
+    }
+
+    /// Returns the faces each loaded buffer or file added that are still loaded, in load order,
+    /// a collection gives one per face.
+    pub fn buffer_faces(&self) -> Vec<Vec<&FaceInfo>> {
+        self.buffers.iter()
+            .map(|ids| ids.iter().filter_map(|id| self.db.face(*id)).collect())
+            .collect()
+    }
+
+    /// Applies `style.face` to a face the family matched.
+    fn select_face(&self, id: ID, style: &FontStyles) -> ID {
+        match style.face {
+            FaceSelector::Index(index) => self.buffers.iter()
+                .find(|ids| ids.contains(&id))
+                .and_then(|ids| ids.iter().find(|id| self.db.face(**id).is_some_and(|face| face.index == index)))
+                .copied()
+                .unwrap_or(id),
+            _ => id,
+        }
+    }
+
+    /// Returns the glyphs of the text in visual order, see `layout`.
     pub fn glyph(&self, style: &FontStyles, text: &str) -> Option<Vec<Glyph>> {
-        let mut chars = text.chars();
-        let mut result = vec![];
-        while let Some(c) = chars.next() {
-            result.push(self.db.glyph(&style.family, c, |g| g)?);
+        Some(self.layout(style, text)?.into_iter().map(|(g, _)| g).collect())
+    }
+
+    /// Resolves the css `style.family` list into the faces glyphs are looked up in:
+    /// the faces of the listed families in order, then every other loaded face.
+    ///
+    /// `style.face` moves a face picked by PostScript name to the front, or swaps each family match
+    /// for the face at an index of its collection.
+    pub fn fallback_chain(&self, style: &FontStyles) -> Rc<[ID]> {
+        let query = style_query(&[], style);
+        let key = (style.family.clone(), query.weight, query.style, query.stretch, style.face.clone());
+        if let Some(chain) = self.chains.borrow().get(&key) {
+            return chain.clone();
+        }
+        let mut chain = vec![];
+        let mut seen = HashSet::new();
+        if let FaceSelector::PostScriptName(name) = &style.face {
+            if let Some(face) = self.db.faces().iter().find(|face| &face.post_script_name == name) {
+                seen.insert(face.id);
+                chain.push(face.id);
+            }
+        }
+        for family in parse_families(&style.family) {
+            if let Some(id) = self.db.query(&style_query(&[family], style)).map(|id| self.select_face(id, style)) {
+                if seen.insert(id) {
+                    chain.push(id);
+                }
+            }
+        }
+        for face in self.db.faces() {
+            if seen.insert(face.id) {
+                chain.push(face.id);
+            }
+        }
+        let chain: Rc<[ID]> = chain.into();
+        self.chains.borrow_mut().insert(key, chain.clone());
+        chain
+    }
+
+    /// Returns the `.notdef` glyph of the first face of the chain.
+    pub fn notdef(&self, style: &FontStyles, chain: &[ID]) -> Option<Glyph> {
+        let id = *chain.first()?;
+        self.db.face_glyph_at(id, 0, &self.variations(id, style), |g| g)
+    }
+
+    /// Returns the variation coordinates of the face for the style, empty for static faces.
+    ///
+    /// The weight and stretch set the `wght` and `wdth` axes the face has, clamped to their range,
+    /// and `style.variations` are applied over them like css `font-variation-settings`.
+    pub fn variations(&self, id: ID, style: &FontStyles) -> Vec<FontVariation> {
+        let axes = self.db.variation_axes(id);
+        let query = style_query(&[], style);
+        let mut variations = vec![];
+        for axis in axes.iter() {
+            let value = match &axis.tag.to_bytes() {
+                b"wght" => query.weight.0 as f32,
+                b"wdth" => stretch_percentage(query.stretch),
+                _ => continue,
+            };
+            variations.push(FontVariation { tag: axis.tag.to_bytes(), value: value.clamp(axis.min_value, axis.max_value) });
+        }
+        for variation in style.variations.iter() {
+            variations.retain(|v| v.tag != variation.tag);
+            variations.push(*variation);
+        }
+        if axes.is_empty() {
+            variations.clear();
+        }
+        variations
+    }
+
+    /// Places the glyphs one after another by their advance widths.
+    ///
+    /// Outlines are relative to the glyph origin, so side bearings are kept as the font defines them
+    /// and glyphs without outline, like spaces, still take their advance.
+    ///
+    /// The text is reordered with the bidi algorithm from `style.direction` first, the glyphs come
+    /// in visual order. Each char is drawn by the first face of `fallback_chain` having it, chars no
+    /// face has get the `.notdef` glyph of the first face, and bold and oblique are synthesized when
+    /// the face lacks them. With the `shaping` feature the pieces are shaped, so ligatures, kerning,
+    /// mirroring and complex scripts follow the font tables, `style.kerning` and `style.features`.
+    /// Variable faces are outlined at their `variations` for the style.
+    ///
+    /// Returns `None` when no font is loaded.
+    pub fn layout(&self, style: &FontStyles, text: &str) -> Option<Vec<(Glyph, PositionedGlyph)>> {
+        self.layout_in(&self.fallback_chain(style), style, text)
+    }
+
+    /// Lays the text out with the faces of a resolved fallback chain, see `layout`.
+    fn layout_in(&self, chain: &[ID], style: &FontStyles, text: &str) -> Option<Vec<(Glyph, PositionedGlyph)>> {
+        let primary = *chain.first()?;
+        let mut x = 0.0;
+        let mut list = vec![];
+        for (run, rtl) in bidi::visual_runs(text, style.direction) {
+            let mut pieces = self.itemize(chain, run);
+            // the pieces of a right to left run are placed from right to left too
+            if rtl {
+                pieces.reverse();
+            }
+            for (id, piece) in pieces {
+                let id = id.unwrap_or(primary);
+                let variations = self.variations(id, style);
+                let (bold, oblique) = self.synthesis(id, style, &variations);
+                let start = x;
+                for (mut glyph, mut position) in self.layout_piece(style, id, &variations, piece, rtl)? {
+                    glyph.synthesize(bold, oblique);
+                    position.x += start;
+                    x += position.advance;
+                    list.push((glyph, position));
+                }
+            }
         }
-        Some(result)
+        Some(list)
+    }
+
+    /// Returns whether the face needs a synthetic bold and a synthetic oblique to match the style,
+    /// when the font matching fell back to a lighter or upright face. A variable face is bold
+    /// enough when its `wght` axis is.
+    fn synthesis(&self, id: ID, style: &FontStyles, variations: &[FontVariation]) -> (bool, bool) {
+        let query = style_query(&[], style);
+        let weight = match variations.iter().find(|variation| &variation.tag == b"wght") {
+            Some(variation) => variation.value,
+            None => self.db.face(id).map_or(0.0, |face| face.weight.0 as f32),
+        };
+        match self.db.face(id) {
+            Some(face) => (query.weight.0 >= 600 && weight < 600.0, query.style != Style::Normal && face.style == Style::Normal),
+            None => (false, false),
+        }
+    }
+
+    /// Splits the text into pieces drawn by a single face, `None` for the chars no face has.
+    fn itemize<'t>(&self, chain: &[ID], text: &'t str) -> Vec<(Option<ID>, &'t str)> {
+        let mut pieces = vec![];
+        let mut start = 0;
+        let mut current = None;
+        for (index, c) in text.char_indices() {
+            let id = chain.iter().copied().find(|id| self.covers(*id, c));
+            if index > 0 && id != current {
+                pieces.push((current, &text[start..index]));
+                start = index;
+            }
+            current = id;
+        }
+        if start < text.len() {
+            pieces.push((current, &text[start..]));
+        }
+        pieces
+    }
+
+    /// Returns the char ranges the face maps, its cmap is read once.
+    fn char_ranges(&self, id: ID) -> CharRanges {
+        self.coverage.borrow_mut()
+            .entry(id)
+            .or_insert_with(|| self.db.coverage(id).into())
+            .clone()
+    }
+
+    /// Returns whether the face maps the char, from its cached cmap coverage.
+    fn covers(&self, id: ID, c: char) -> bool {
+        let ranges = self.char_ranges(id);
+        let c = c as u32;
+        ranges.binary_search_by(|&(start, end)| {
+            if end < c {
+                Ordering::Less
+            } else if start > c {
+                Ordering::Greater
+            } else {
+                Ordering::Equal
+            }
+        }).is_ok()
+    }
+
+    /// Lays out a piece of a single direction and face starting at 0, missing chars get `.notdef`.
+    fn layout_piece(&self, style: &FontStyles, id: ID, variations: &[FontVariation], text: &str, rtl: bool) -> Option<Vec<(Glyph, PositionedGlyph)>> {
+        #[cfg(feature = "shaping")]
+        {
+            shaping::shape(&self.db, id, style, variations, text, rtl)
+        }
+        #[cfg(not(feature = "shaping"))]
+        {
+            let text: String = if rtl { bidi::reverse_clusters(text) } else { text.to_string() };
+            let glyphs = self.db.face_glyphs_at(id, &self.db.glyph_indices(id, &text), variations)?;
+            let mut x = 0.0;
+            Some(glyphs.into_iter().map(|g| {
+                let advance = g.advance_width(style.size);
+                let position = PositionedGlyph { glyph_id: g.id(), x, y: 0.0, advance };
+                x += advance;
+                (g, position)
+            }).collect())
+        }
+    }
+
+    /// Measures the text with the metrics of the first face of the fallback chain.
+    pub fn measure_text(&self, style: &FontStyles, text: &str) -> Option<TextMetrics> {
+        let chain = self.fallback_chain(style);
+        let layout = self.layout_in(&chain, style, text)?;
+        let bbox = layout.iter().fold(None, |bbox, (g, position)| merge_bbox(bbox, &placed_path(g, position, style.size)));
+        self.text_metrics(style, &chain, &layout, bbox)
+    }
+
+    /// Lays the text out once for both its outlines and its metrics.
+    pub fn layout_text(&self, style: &FontStyles, text: &str) -> Option<(painter_core::font::Glyph, TextMetrics)> {
+        let chain = self.fallback_chain(style);
+        let layout = self.layout_in(&chain, style, text)?;
+        let (glyph, bbox) = outline(style, &layout);
+        Some((glyph, self.text_metrics(style, &chain, &layout, bbox)?))
+    }
+
+    /// The metrics of a layout whose glyph outlines cover `bbox`.
+    fn text_metrics(&self, style: &FontStyles, chain: &[ID], layout: &[(Glyph, PositionedGlyph)], bbox: Option<BoundingBox>) -> Option<TextMetrics> {
+        let size = style.size;
+        let font = self.notdef(style, chain)?;
+        let width = layout.iter().map(|(_, position)| position.advance).sum();
+        let bbox = bbox.unwrap_or(BoundingBox::new(0.0, 0.0));
+
+        let ascent = font.ascent(size);
+        let descent = -font.descent(size);
+        // the em square is split in the ascent to descent ratio
+        let em_height_ascent = if ascent + descent > 0.0 { size * ascent / (ascent + descent) } else { size };
+        Some(TextMetrics {
+            width,
+            actual_bounding_box_left: -bbox.x1,
+            actual_bounding_box_right: bbox.x2,
+            actual_bounding_box_ascent: -bbox.y1,
+            actual_bounding_box_descent: bbox.y2,
+            font_bounding_box_ascent: ascent,
+            font_bounding_box_descent: descent,
+            em_height_ascent,
+            em_height_descent: size - em_height_ascent,
+            // fonts rarely ship a BASE table, these are the ratios browsers fall back to
+            hanging_baseline: ascent * 0.8,
+            alphabetic_baseline: 0.0,
+            ideographic_baseline: -descent,
+        })
     }
 
     pub fn typeset() {}
 }
 
+/// The outline of a laid out glyph, moved to its position.
+fn placed_path(g: &Glyph, position: &PositionedGlyph, size: f32) -> PathData {
+    let mut path = g.path(size);
+    path.transform(Transform::new_translate(position.x, position.y));
+    path
+}
+
+fn merge_bbox(bbox: Option<BoundingBox>, path: &PathData) -> Option<BoundingBox> {
+    match (bbox, path.get_bounding_box()) {
+        (Some(bbox), Some(glyph_bbox)) => Some(bbox.merge(&glyph_bbox)),
+        (bbox, glyph_bbox) => bbox.or(glyph_bbox),
+    }
+}
+
+/// Joins the outlines and color parts of a layout into one glyph, with the bounding box
+/// of the glyph outlines.
+fn outline(style: &FontStyles, layout: &[(Glyph, PositionedGlyph)]) -> (painter_core::font::Glyph, Option<BoundingBox>) {
+    let mut path_data = PathData::new();
+    let mut colors = vec![];
+    let mut bbox = None;
+    for (g, position) in layout.iter() {
+        let mut path = placed_path(g, position, style.size);
+        bbox = merge_bbox(bbox, &path);
+        let translate = Transform::new_translate(position.x, position.y);
+        let parts = g.color_parts(style.size);
+        // color glyphs are drawn from their parts only, their outline is a monochrome fallback
+        if parts.is_empty() {
+            path_data.append(&mut path);
+        }
+        for mut part in parts {
+            match &mut part {
+                ColorGlyph::Layer { path, .. } => path.transform(translate),
+                ColorGlyph::Image { transform, .. } => transform.prepend(&translate),
+            }
+            colors.push(part);
+        }
+    }
+    let glyph = painter_core::font::Glyph {
+        path: path_data,
+        fill_rule: Default::default(),
+        transform: Default::default(),
+        run: layout.iter().map(|(_, position)| *position).collect(),
+        colors,
+    };
+    (glyph, bbox)
+}
+
+/// Builds the face query of the families for the weight, style and stretch of the font style.
+fn style_query<'a>(families: &'a [Family<'a>], style: &FontStyles) -> Query<'a> {
+    let weight = match style.weight.as_str() {
+        "bold" => Weight::BOLD,
+        weight => weight.parse().map(Weight).unwrap_or(Weight::NORMAL),
+    };
+    let stretch = match style.stretch.as_str() {
+        "ultra-condensed" => Stretch::UltraCondensed,
+        "extra-condensed" => Stretch::ExtraCondensed,
+        "condensed" => Stretch::Condensed,
+        "semi-condensed" => Stretch::SemiCondensed,
+        "semi-expanded" => Stretch::SemiExpanded,
+        "expanded" => Stretch::Expanded,
+        "extra-expanded" => Stretch::ExtraExpanded,
+        "ultra-expanded" => Stretch::UltraExpanded,
+        _ => Stretch::Normal,
+    };
+    let style = match style.style {
+        painter_core::font::FontStyle::Normal => Style::Normal,
+        painter_core::font::FontStyle::Italic => Style::Italic,
+        painter_core::font::FontStyle::Oblique => Style::Oblique,
+    };
+    Query { families, weight, stretch, style }
+}
+
+/// The css `font-stretch` keyword of a stretch.
+fn stretch_keyword(stretch: Stretch) -> &'static str {
+    match stretch {
+        Stretch::UltraCondensed => "ultra-condensed",
+        Stretch::ExtraCondensed => "extra-condensed",
+        Stretch::Condensed => "condensed",
+        Stretch::SemiCondensed => "semi-condensed",
+        Stretch::Normal => "normal",
+        Stretch::SemiExpanded => "semi-expanded",
+        Stretch::Expanded => "expanded",
+        Stretch::ExtraExpanded => "extra-expanded",
+        Stretch::UltraExpanded => "ultra-expanded",
+    }
+}
+
+/// The `wdth` axis value of a stretch, in percent of the normal width.
+fn stretch_percentage(stretch: Stretch) -> f32 {
+    match stretch {
+        Stretch::UltraCondensed => 50.0,
+        Stretch::ExtraCondensed => 62.5,
+        Stretch::Condensed => 75.0,
+        Stretch::SemiCondensed => 87.5,
+        Stretch::Normal => 100.0,
+        Stretch::SemiExpanded => 112.5,
+        Stretch::Expanded => 125.0,
+        Stretch::ExtraExpanded => 150.0,
+        Stretch::UltraExpanded => 200.0,
+    }
+}
+
 impl FontBucket for FontDB {
     fn default_glyph(&self, style: &FontStyles) -> painter_core::font::Glyph {
-        todo!()
+        match self.notdef(style, &self.fallback_chain(style)) {
+            Some(g) => painter_core::font::Glyph {
+                path: g.path(style.size),
+                fill_rule: Default::default(),
+                transform: Default::default(),
+                run: vec![PositionedGlyph { glyph_id: 0, x: 0.0, y: 0.0, advance: g.advance_width(style.size) }],
+                colors: vec![],
+            },
+            None => Default::default(),
+        }
     }
 
     fn get_glyph(&mut self, style: &FontStyles, text: &str) -> Option<painter_core::font::Glyph> {
-        let list = self.glyph(style, text)?;
-        let mut path_data = PathData::new();
-        for g in list.iter() {
-            let mut path = g.path(style.size);
-            let width = path_data.get_bounding_box().unwrap_or(BoundingBox::new(0.0, 0.0)).get_width();
-            path.transform(Transform::new_translate(width, 0.0));
-            path_data.append(&mut path);
+        Some(outline(style, &self.layout(style, text)?).0)
+    }
+
+    fn measure_text(&mut self, style: &FontStyles, text: &str) -> Option<TextMetrics> {
+        FontDB::measure_text(self, style, text)
+    }
+
+    fn layout_text(&mut self, style: &FontStyles, text: &str) -> Option<(painter_core::font::Glyph, TextMetrics)> {
+        FontDB::layout_text(self, style, text)
+    }
+
+    fn load_font(&mut self, buf: &[u8]) -> Result<Vec<FaceId>, FontLoadError> {
+        FontDB::load_font(self, buf)
+    }
+
+    fn faces(&self) -> Vec<LoadedFace> {
+        FontDB::faces(self)
+    }
+
+    fn remove_face(&mut self, id: FaceId) -> bool {
+        FontDB::remove_face(self, id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use painter_core::backend::skia_cpu::{ImageDataRender, SkiaCPURender};
+    use painter_core::backend::svg::SvgRender;
+    use painter_core::context2d::Context;
+    use painter_core::font::{ColorGlyph, FaceId, FaceSelector, FontBucket, FontLoadError, FontStyles, FontVariation};
+    use painter_core::paint::Paint;
+    use painter_core::paint::color::Color;
+    use painter_core::PathData;
+    use crate::FontDB;
+
+    #[test]
+    fn measure_text() {
+        let file = include_bytes!("../test/zc2016.woff") as &[u8];
+        let mut font_db = FontDB::new();
+        font_db.load_font(file).unwrap();
+        let mut ctx = Context::new_wh(200.0, 200.0);
+        ctx.set_font_family("HappyZcool-2016");
+        ctx.set_font_size(40.0);
+        let style = ctx.get_context_attributes().get_font_style();
+        let glyphs = font_db.glyph(&style, "hi 家").unwrap();
+        let advance: f32 = glyphs.iter().map(|g| g.advance_width(40.0)).sum();
+
+        let metrics = ctx.measure_text("hi 家", Some(&mut font_db)).unwrap();
+        assert_eq!(metrics.width, advance);
+        assert!(metrics.actual_bounding_box_right > 0.0 && metrics.actual_bounding_box_right <= metrics.width + 1.0);
+        assert!(metrics.actual_bounding_box_ascent > 0.0);
+        assert!(metrics.font_bounding_box_ascent >= metrics.actual_bounding_box_ascent);
+        assert!((metrics.em_height_ascent + metrics.em_height_descent - 40.0).abs() < 0.001);
+        assert_eq!(metrics.ideographic_baseline, -metrics.font_bounding_box_descent);
+
+        let empty = ctx.measure_text("", Some(&mut font_db)).unwrap();
+        assert_eq!(empty.width, 0.0);
+        assert_eq!(empty.font_bounding_box_ascent, metrics.font_bounding_box_ascent);
+        assert!(ctx.measure_text("hi", None).is_none());
+    }
+
+    #[test]
+    fn layout() {
+        let file = include_bytes!("../test/zc2016.woff") as &[u8];
+        let mut font_db = FontDB::new();
+        font_db.load_font(file).unwrap();
+        let style = FontStyles { family: String::from("HappyZcool-2016"), size: 40.0, ..FontStyles::default() };
+
+        // the space has no outline but still moves the pen
+        let glyph = font_db.get_glyph(&style, "hi hi").unwrap();
+        let advance = |text: &str| font_db.glyph(&style, text).unwrap()[0].advance_width(40.0);
+        let xs: Vec<f32> = glyph.run.iter().map(|g| g.x).collect();
+        assert_eq!(glyph.run.len(), 5);
+        assert_eq!(xs[1], advance("h"));
+        assert_eq!(xs[3], advance("h") + advance("i") + advance(" "));
+        assert!(glyph.run.iter().all(|g| g.y == 0.0 && g.glyph_id != 0));
+
+        let metrics = font_db.measure_text(&style, "hi hi").unwrap();
+        let last = glyph.run.last().unwrap();
+        assert_eq!(metrics.width, last.x + last.advance);
+        let bbox = glyph.path.get_bounding_box().unwrap();
+        assert_eq!((bbox.x1, bbox.x2), (-metrics.actual_bounding_box_left, metrics.actual_bounding_box_right));
+
+        // one layout gives the same glyphs and metrics
+        let (text_glyph, text_metrics) = font_db.layout_text(&style, "hi hi").unwrap();
+        assert_eq!(text_glyph.run, glyph.run);
+        assert_eq!(String::from(&text_glyph.path), String::from(&glyph.path));
+        assert_eq!(text_metrics, metrics);
+    }
+
+    /// Builds a GSUB or GPOS table with a single lookup used by `feature` for every script.
+    #[cfg(feature = "shaping")]
+    fn layout_table(feature: &[u8; 4], lookup_type: u16, subtable: &[u8]) -> Vec<u8> {
+        // the script list points the DFLT script at a default language system using feature 0
+        let mut table = vec![0, 1, 0, 0, 0, 10, 0, 30, 0, 44];
+        table.extend_from_slice(&[0, 1]);
+        table.extend_from_slice(b"DFLT");
+        table.extend_from_slice(&[0, 8, 0, 4, 0, 0, 0, 0, 0xff, 0xff, 0, 1, 0, 0]);
+        table.extend_from_slice(&[0, 1]);
+        table.extend_from_slice(feature);
+        table.extend_from_slice(&[0, 8, 0, 0, 0, 1, 0, 0]);
+        table.extend_from_slice(&[0, 1, 0, 4]);
+        table.extend_from_slice(&lookup_type.to_be_bytes());
+        table.extend_from_slice(&[0, 0, 0, 1, 0, 8]);
+        table.extend_from_slice(subtable);
+        table
+    }
+
+    /// Adds a `liga` ligature replacing the glyph pair with `ligature`, and a `kern` pair
+    /// moving the second glyph of the pair 100 units closer to the first.
+    #[cfg(feature = "shaping")]
+    fn shaping_font(font: &[u8], pair: [u16; 2], ligature: u16) -> Vec<u8> {
+        let [first, second] = pair.map(u16::to_be_bytes);
+        // one ligature set covering the first glyph, with one ligature of two components
+        let mut liga = vec![0, 1, 0, 8, 0, 1, 0, 14, 0, 1, 0, 1, first[0], first[1], 0, 1, 0, 4];
+        liga.extend_from_slice(&ligature.to_be_bytes());
+        liga.extend_from_slice(&[0, 2, second[0], second[1]]);
+        // one pair set covering the first glyph, adjusting its x advance
+        let mut kern = vec![0, 1, 0, 12, 0, 4, 0, 0, 0, 1, 0, 18, 0, 1, 0, 1, first[0], first[1], 0, 1, second[0], second[1]];
+        kern.extend_from_slice(&(-100i16).to_be_bytes());
+
+        let mut tables = crate::woff2::test::read_tables(font);
+        tables.push((*b"GSUB", layout_table(b"liga", 4, &liga)));
+        tables.push((*b"GPOS", layout_table(b"kern", 2, &kern)));
+        crate::woff2::build_sfnt(0x00010000, tables)
+    }
+
+    #[cfg(feature = "shaping")]
+    #[test]
+    fn shaping() {
+        use painter_core::font::{FontFeature, FontKerning};
+
+        let font = crate::woff::decompress_woff(include_bytes!("../test/zc2016.woff")).unwrap();
+        let face = ttf_parser::Face::parse(&font, 0).unwrap();
+        let glyph = |c: char| face.glyph_index(c).unwrap().0;
+        let kerning = 100.0 * 40.0 / face.units_per_em() as f32;
+        let mut font_db = FontDB::new();
+        font_db.load_font(&shaping_font(&font, [glyph('h'), glyph('i')], glyph('家'))).unwrap();
+        let style = FontStyles { family: String::from("HappyZcool-2016"), size: 40.0, ..FontStyles::default() };
+        let run = |style: &FontStyles, text: &str| font_db.layout(style, text).unwrap().into_iter().map(|(_, position)| position).collect::<Vec<_>>();
+        let ids = |style: &FontStyles, text: &str| run(style, text).iter().map(|g| g.glyph_id).collect::<Vec<_>>();
+
+        // ligatures are on by default, the pair becomes one glyph
+        assert_eq!(ids(&style, "hi h"), vec![glyph('家'), glyph(' '), glyph('h')]);
+        let no_liga = FontStyles { features: vec![FontFeature { tag: *b"liga", value: 0 }], ..style.clone() };
+        assert_eq!(ids(&no_liga, "hi h"), vec![glyph('h'), glyph('i'), glyph(' '), glyph('h')]);
+
+        // without the ligature the pair is kerned, unless kerning is off
+        let advance = font_db.glyph(&style, "h").unwrap()[0].advance_width(40.0);
+        let kerned = run(&no_liga, "hi");
+        assert!((kerned[0].advance - (advance - kerning)).abs() < 0.001);
+        assert!((kerned[1].x - (advance - kerning)).abs() < 0.001);
+        let no_kern = FontStyles { kerning: FontKerning::None, ..no_liga.clone() };
+        assert!((run(&no_kern, "hi")[0].advance - advance).abs() < 0.001);
+        let kern_off = FontStyles { features: vec![FontFeature { tag: *b"liga", value: 0 }, FontFeature { tag: *b"kern", value: 0 }], ..style.clone() };
+        assert_eq!(run(&kern_off, "hi"), run(&no_kern, "hi"));
+        let kern_on = FontStyles { features: vec![FontFeature { tag: *b"liga", value: 0 }, FontFeature { tag: *b"kern", value: 1 }], ..style.clone() };
+        assert_eq!(run(&kern_on, "hi"), kerned);
+
+        // the cmap glyphs are kept for chars the tables don't touch
+        let glyphs = font_db.glyph(&style, "ih 家").unwrap();
+        assert_eq!(ids(&style, "ih 家"), glyphs.iter().map(|g| g.id()).collect::<Vec<_>>());
+        assert_eq!(run(&style, "hi \u{10FFFF}").last().unwrap().glyph_id, 0);
+    }
+
+    #[test]
+    fn fallback() {
+        let file = include_bytes!("../test/zc2016.woff") as &[u8];
+        let mut font_db = FontDB::new();
+        let style = FontStyles { family: String::from(r#"Missing, "HappyZcool-2016", serif"#), size: 40.0, ..FontStyles::default() };
+        assert!(font_db.get_glyph(&style, "hi").is_none());
+        assert!(font_db.default_glyph(&style).path.is_empty());
+
+        font_db.load_font(file).unwrap();
+        assert_eq!(font_db.fallback_chain(&style).len(), 1);
+        // the closest face is picked when none matches the weight, style and stretch
+        let bold = FontStyles { weight: String::from("700"), style: painter_core::font::FontStyle::Italic, stretch: String::from("condensed"), ..style.clone() };
+        assert_eq!(font_db.fallback_chain(&bold), font_db.fallback_chain(&style));
+
+        // the regular face is emboldened and slanted instead, advances are kept
+        let regular = font_db.get_glyph(&style, "h").unwrap();
+        let synthetic = font_db.get_glyph(&bold, "h").unwrap();
+        assert_eq!(synthetic.run, regular.run);
+        let (regular, synthetic) = (regular.path.get_bounding_box().unwrap(), synthetic.path.get_bounding_box().unwrap());
+        // curve extremes and corner miters move a bit further than the 1/48 em offset
+        let grown = synthetic.get_height() - regular.get_height();
+        assert!(grown > 40.0 / 24.0 - 0.01 && grown < 40.0 / 12.0);
+        // slanted further right than the emboldening alone
+        assert!(synthetic.x2 - regular.x2 > 40.0 / 24.0);
+        // a char missing from every face doesn't drop the whole text
+        let glyph = font_db.get_glyph(&style, "h\u{10FFFF}i").unwrap();
+        let ids: Vec<u16> = glyph.run.iter().map(|g| g.glyph_id).collect();
+        assert_eq!(ids.len(), 3);
+        assert!(ids[0] != 0 && ids[1] == 0 && ids[2] != 0);
+        let notdef = font_db.default_glyph(&style);
+        assert_eq!(glyph.run[1].advance, notdef.run[0].advance);
+        assert_eq!(glyph.run[2].x, glyph.run[1].x + notdef.run[0].advance);
+        // the coverage of each face is read once, for the faces the chars were looked up in
+        assert_eq!(font_db.coverage.borrow().len(), 1);
+        assert!(font_db.covers(font_db.fallback_chain(&style)[0], 'h') && !font_db.covers(font_db.fallback_chain(&style)[0], '\u{10FFFF}'));
+
+        // a chain is resolved once, until faces are loaded or removed
+        assert!(std::rc::Rc::ptr_eq(&font_db.fallback_chain(&style), &font_db.fallback_chain(&style)));
+        font_db.load_font(file).unwrap();
+        assert_eq!(font_db.fallback_chain(&style).len(), 2);
+        assert!(font_db.remove_face(FaceId(1)));
+        assert_eq!(font_db.fallback_chain(&style).len(), 1);
+    }
+
+    /// Adds a COLR glyph made of two layers and an sbix glyph with a blue 4x4 bitmap to the font.
+    fn color_font(font: &[u8], colr: u16, layers: [u16; 2], sbix: u16) -> Vec<u8> {
+        let mut tables = crate::woff2::test::read_tables(font);
+        let maxp = &tables.iter().find(|(tag, _)| tag == b"maxp").unwrap().1;
+        let num_glyphs = u16::from_be_bytes([maxp[4], maxp[5]]);
+
+        let mut colr_table = vec![0, 0, 0, 1, 0, 0, 0, 14, 0, 0, 0, 20, 0, 2];
+        for value in [colr, 0, 2, layers[0], 0, layers[1], 0xffff] {
+            colr_table.extend_from_slice(&value.to_be_bytes());
         }
-        Some(painter_core::font::Glyph {
-            path: path_data,
-            fill_rule: Default::default(),
-            transform: Default::default(),
-        })
+        // one palette with a single red entry
+        let cpal_table = vec![0, 0, 0, 1, 0, 1, 0, 1, 0, 0, 0, 14, 0, 0, 0, 0, 255, 255];
+
+        let mut png = vec![];
+        {
+            let mut encoder = png::Encoder::new(&mut png, 4, 4);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.write_header().unwrap().write_image_data(&[0, 0, 255, 255].repeat(16)).unwrap();
+        }
+        let mut sbix_table = vec![0, 1, 0, 1, 0, 0, 0, 1, 0, 0, 0, 12];
+        let header = 4 + 4 * (num_glyphs as u32 + 1);
+        sbix_table.extend_from_slice(&[0, 4, 0, 72]);
+        for glyph_id in 0..=num_glyphs {
+            let offset = if glyph_id > sbix { header + 8 + png.len() as u32 } else { header };
+            sbix_table.extend_from_slice(&offset.to_be_bytes());
+        }
+        sbix_table.extend_from_slice(&[0, 0, 0, 0]);
+        sbix_table.extend_from_slice(b"png ");
+        sbix_table.extend_from_slice(&png);
+
+        tables.push((*b"COLR", colr_table));
+        tables.push((*b"CPAL", cpal_table));
+        tables.push((*b"sbix", sbix_table));
+        crate::woff2::build_sfnt(0x00010000, tables)
+    }
+
+    #[test]
+    fn color_glyphs() {
+        let font = crate::woff::decompress_woff(include_bytes!("../test/zc2016.woff")).unwrap();
+        let face = ttf_parser::Face::parse(&font, 0).unwrap();
+        let glyph = |c: char| face.glyph_index(c).unwrap().0;
+        let mut font_db = FontDB::new();
+        font_db.load_font(&color_font(&font, glyph('家'), [glyph('字'), glyph('家')], glyph('字'))).unwrap();
+        let style = FontStyles { family: String::from("HappyZcool-2016"), size: 40.0, ..FontStyles::default() };
+
+        let bounds = |path: &PathData| path.get_bounding_box().map(|bbox| (bbox.x1, bbox.y1, bbox.x2, bbox.y2));
+        let plain = font_db.layout(&style, "家").unwrap()[0].0.path(40.0);
+        let glyph = font_db.get_glyph(&style, "家字h").unwrap();
+        // only the glyph without colors is left in the outline
+        let h = bounds(&font_db.get_glyph(&style, "h").unwrap().path).unwrap();
+        assert_eq!(bounds(&glyph.path), Some((h.0 + 80.0, h.1, h.2 + 80.0, h.3)));
+        match &glyph.colors[..] {
+            [ColorGlyph::Layer { color: Some(red), .. }, ColorGlyph::Layer { path, color: None }, ColorGlyph::Image { image, transform }] => {
+                assert_eq!(*red, Color::from_rgba8(255, 0, 0, 255));
+                assert_eq!(bounds(path), bounds(&plain));
+                assert_eq!((image.width(), image.height()), (4, 4));
+                // the 4 pixels per em strike covers the em square of the second glyph
+                assert_eq!(transform.apply(0.0, 0.0), (40.0, -40.0));
+                assert_eq!(transform.apply(4.0, 4.0), (80.0, 0.0));
+            }
+            colors => panic!("{:?}", colors),
+        }
+
+        let mut ctx = Context::new_wh(100.0, 60.0);
+        ctx.set_fill_style(Paint::from_color_rgba8(0, 255, 0, 255));
+        ctx.set_font("40px HappyZcool-2016").unwrap();
+        ctx.fill_text("家字", 10.0, 50.0, None, Some(&mut font_db)).unwrap();
+        let data = ctx.render(Box::new(ImageDataRender(SkiaCPURender::new(100, 60))));
+        let pixel = |x: usize, y: usize| &data[(y * 100 + x) * 4..(y * 100 + x) * 4 + 4];
+        assert_eq!(pixel(70, 30), &[0, 0, 255, 255]);
+        let colors: Vec<&[u8]> = (0..50).flat_map(|x| (10..50).map(move |y| (x, y))).map(|(x, y)| pixel(x, y)).collect();
+        assert!(colors.contains(&&[255, 0, 0, 255][..]));
+        assert!(colors.contains(&&[0, 255, 0, 255][..]));
+
+        let svg = String::from_utf8(ctx.render(Box::new(SvgRender::new(100.0, 60.0)))).unwrap();
+        assert!(svg.contains("<image"));
+    }
+
+    /// Makes the font variable with a `wght` axis from 100 to 900 that moves `glyph_id` 100 units right at 900.
+    fn variable_font(font: &[u8], glyph_id: u16) -> Vec<u8> {
+        let mut tables = crate::woff2::test::read_tables(font);
+        let table = |tag: &[u8; 4]| tables.iter().find(|(t, _)| t == tag).unwrap().1.clone();
+        let (head, maxp, loca, glyf) = (table(b"head"), table(b"maxp"), table(b"loca"), table(b"glyf"));
+        let num_glyphs = u16::from_be_bytes([maxp[4], maxp[5]]);
+        let offset = if head[51] == 0 {
+            u16::from_be_bytes([loca[glyph_id as usize * 2], loca[glyph_id as usize * 2 + 1]]) as usize * 2
+        } else {
+            u32::from_be_bytes(loca[glyph_id as usize * 4..glyph_id as usize * 4 + 4].try_into().unwrap()) as usize
+        };
+        let contours = u16::from_be_bytes([glyf[offset], glyf[offset + 1]]) as usize;
+        let end_point = offset + 10 + (contours - 1) * 2;
+        let points = u16::from_be_bytes([glyf[end_point], glyf[end_point + 1]]) as usize + 1;
+
+        let mut fvar = vec![0, 1, 0, 0, 0, 16, 0, 2, 0, 1, 0, 20, 0, 0, 0, 8];
+        fvar.extend_from_slice(b"wght");
+        for value in [100u32, 400, 900] {
+            fvar.extend_from_slice(&(value << 16).to_be_bytes());
+        }
+        fvar.extend_from_slice(&[0, 0, 1, 0]);
+
+        // every point moves by 100 in x, the 4 phantom points stay
+        let mut deltas = vec![0];
+        for chunk in vec![100u8; points].chunks(64) {
+            deltas.push(chunk.len() as u8 - 1);
+            deltas.extend_from_slice(chunk);
+        }
+        deltas.push(0x80 | 3);
+        for chunk in (0..points + 4).collect::<Vec<_>>().chunks(64) {
+            deltas.push(0x80 | (chunk.len() as u8 - 1));
+        }
+        let mut data = vec![0, 1, 0, 10];
+        data.extend_from_slice(&(deltas.len() as u16).to_be_bytes());
+        data.extend_from_slice(&[0xa0, 0, 0x40, 0]);
+        data.extend_from_slice(&deltas);
+
+        let array_offset = 20 + 4 * (num_glyphs as u32 + 1);
+        let mut gvar = vec![0, 1, 0, 0, 0, 1, 0, 0];
+        gvar.extend_from_slice(&array_offset.to_be_bytes());
+        gvar.extend_from_slice(&num_glyphs.to_be_bytes());
+        gvar.extend_from_slice(&[0, 1]);
+        gvar.extend_from_slice(&array_offset.to_be_bytes());
+        for id in 0..=num_glyphs {
+            let offset = if id > glyph_id { data.len() as u32 } else { 0 };
+            gvar.extend_from_slice(&offset.to_be_bytes());
+        }
+        gvar.extend_from_slice(&data);
+
+        tables.push((*b"fvar", fvar));
+        tables.push((*b"gvar", gvar));
+        crate::woff2::build_sfnt(0x00010000, tables)
+    }
+
+    #[test]
+    fn variations() {
+        let font = crate::woff::decompress_woff(include_bytes!("../test/zc2016.woff")).unwrap();
+        let face = ttf_parser::Face::parse(&font, 0).unwrap();
+        let shift = 100.0 * 40.0 / face.units_per_em() as f32;
+        let mut font_db = FontDB::new();
+        font_db.load_font(&variable_font(&font, face.glyph_index('h').unwrap().0)).unwrap();
+        let style = FontStyles { family: String::from("HappyZcool-2016"), size: 40.0, ..FontStyles::default() };
+        let id = font_db.fallback_chain(&style)[0];
+        let bounds = |font_db: &mut FontDB, style: &FontStyles| {
+            let bbox = font_db.get_glyph(style, "h").unwrap().path.get_bounding_box().unwrap();
+            (bbox.x1, bbox.x2)
+        };
+
+        let wght = |value: f32| vec![FontVariation { tag: *b"wght", value }];
+        assert_eq!(font_db.variations(id, &style), wght(400.0));
+        let bold = FontStyles { weight: String::from("900"), stretch: String::from("condensed"), ..style.clone() };
+        assert_eq!(font_db.variations(id, &bold), wght(900.0));
+        assert_eq!(font_db.variations(id, &FontStyles { weight: String::from("1000"), ..style.clone() }), wght(900.0));
+        let custom = FontStyles { variations: vec![FontVariation { tag: *b"wght", value: 650.0 }, FontVariation { tag: *b"XTRA", value: 1.0 }], ..bold.clone() };
+        assert_eq!(font_db.variations(id, &custom), vec![custom.variations[0], custom.variations[1]]);
+
+        // the weight comes from the axis, so the glyph moves instead of getting a synthetic bold
+        let (regular, heavy, medium) = (bounds(&mut font_db, &style), bounds(&mut font_db, &bold), bounds(&mut font_db, &custom));
+        assert!((heavy.0 - regular.0 - shift).abs() < 0.01 && (heavy.1 - regular.1 - shift).abs() < 0.01);
+        assert!((medium.0 - regular.0 - shift / 2.0).abs() < 0.01);
+
+        let mut static_db = FontDB::new();
+        static_db.load_font(&font).unwrap();
+        assert!(static_db.variations(static_db.fallback_chain(&style)[0], &custom).is_empty());
     }
 
-    fn load_font(&mut self, buf: &[u8]) -> Option<()> {
-        self.load_font(buf)?;
-        Some(())
+    /// Replaces the name table so the font is the `family` family, with PostScript name `family-Regular`.
+    fn renamed(font: &[u8], family: &str) -> Vec<u8> {
+        let names = [(1u16, family.to_string()), (2, String::from("Regular")), (4, family.to_string()), (6, format!("{}-Regular", family))];
+        let mut name = vec![0, 0];
+        name.extend_from_slice(&(names.len() as u16).to_be_bytes());
+        name.extend_from_slice(&(6 + 12 * names.len() as u16).to_be_bytes());
+        let mut strings = vec![];
+        for (id, text) in names.iter() {
+            let text: Vec<u8> = text.encode_utf16().flat_map(|c| c.to_be_bytes()).collect();
+            for value in [3, 1, 0x409, *id, text.len() as u16, strings.len() as u16] {
+                name.extend_from_slice(&value.to_be_bytes());
+            }
+            strings.extend_from_slice(&text);
+        }
+        name.extend_from_slice(&strings);
+        let mut tables = crate::woff2::test::read_tables(font);
+        tables.retain(|(tag, _)| tag != b"name");
+        tables.push((*b"name", name));
+        crate::woff2::build_sfnt(0x00010000, tables)
+    }
+
+    /// Packs the fonts into a `.ttc` collection, each keeping its own tables.
+    fn collection(fonts: &[Vec<u8>]) -> Vec<u8> {
+        let mut data = b"ttcf".to_vec();
+        data.extend_from_slice(&[0, 1, 0, 0]);
+        data.extend_from_slice(&(fonts.len() as u32).to_be_bytes());
+        let mut offset = 12 + 4 * fonts.len();
+        for font in fonts {
+            data.extend_from_slice(&(offset as u32).to_be_bytes());
+            offset += font.len();
+        }
+        for font in fonts {
+            let base = data.len() as u32;
+            let mut font = font.clone();
+            let num_tables = u16::from_be_bytes([font[4], font[5]]) as usize;
+            for record in (12..12 + 16 * num_tables).step_by(16) {
+                let table = u32::from_be_bytes(font[record + 8..record + 12].try_into().unwrap()) + base;
+                font[record + 8..record + 12].copy_from_slice(&table.to_be_bytes());
+            }
+            data.extend_from_slice(&font);
+        }
+        data
+    }
+
+    #[test]
+    fn collection_faces() {
+        let font = crate::woff::decompress_woff(include_bytes!("../test/zc2016.woff")).unwrap();
+        let ttc = collection(&[font.clone(), renamed(&font, "Second")]);
+        assert_eq!(crate::check::check_type(&ttc).unwrap(), (String::from("ttc"), false));
+        let mut otc = ttc.clone();
+        otc[20..24].copy_from_slice(b"OTTO");
+        assert_eq!(crate::check::check_type(&otc).unwrap(), (String::from("otc"), false));
+
+        let mut font_db = FontDB::new();
+        font_db.load_font(&ttc).unwrap();
+        let buffers: Vec<Vec<(&str, u32)>> = font_db.buffer_faces().iter()
+            .map(|faces| faces.iter().map(|face| (face.family.as_str(), face.index)).collect())
+            .collect();
+        assert_eq!(buffers, vec![vec![("HappyZcool-2016", 0), ("Second", 1)]]);
+        assert_eq!(font_db.buffer_faces()[0][1].post_script_name, "Second-Regular");
+
+        let style = FontStyles { family: String::from("HappyZcool-2016"), size: 40.0, ..FontStyles::default() };
+        let family = |style: &FontStyles| font_db.db.face(font_db.fallback_chain(style)[0]).map(|face| (face.family.clone(), face.index));
+        assert_eq!(family(&style), Some((String::from("HappyZcool-2016"), 0)));
+        // the index is looked up in the collection the family matched in
+        let second = FontStyles { face: FaceSelector::Index(1), ..style.clone() };
+        assert_eq!(family(&second), Some((String::from("Second"), 1)));
+        assert_eq!(family(&FontStyles { face: FaceSelector::Index(5), ..style.clone() }), family(&style));
+        let named = FontStyles { face: FaceSelector::PostScriptName(String::from("Second-Regular")), ..style.clone() };
+        assert_eq!(family(&named), Some((String::from("Second"), 1)));
+        assert!(font_db.get_glyph(&second, "h").unwrap().path.get_bounding_box().is_some());
+    }
+
+    #[test]
+    fn inventory() {
+        let font = crate::woff::decompress_woff(include_bytes!("../test/zc2016.woff")).unwrap();
+        let face = ttf_parser::Face::parse(&font, 0).unwrap();
+        let mut font_db = FontDB::new();
+        assert_eq!(font_db.load_font(&font), Ok(vec![FaceId(0)]));
+        assert_eq!(font_db.load_font(&collection(&[font.clone(), renamed(&font, "Second")])), Ok(vec![FaceId(1), FaceId(2)]));
+        assert_eq!(font_db.load_font(b"nope"), Err(FontLoadError::BadSignature));
+
+        let faces = font_db.faces();
+        let names: Vec<(FaceId, &str, &str)> = faces.iter().map(|face| (face.id, face.family.as_str(), face.post_script_name.as_str())).collect();
+        assert_eq!(names[2], (FaceId(2), "Second", "Second-Regular"));
+        assert_eq!((names[0].0, names[1].0), (FaceId(0), FaceId(1)));
+        let first = &faces[0];
+        assert_eq!((first.weight, first.style, first.stretch.as_str(), first.monospaced), (400, painter_core::font::FontStyle::Normal, "normal", false));
+        assert_eq!(first.glyph_count, face.number_of_glyphs());
+        let covers = |c: char| first.unicode_ranges.iter().any(|(start, end)| (*start..=*end).contains(&(c as u32)));
+        assert!(covers('h') && covers('中') && !covers('\u{10ffff}'));
+        assert!(first.unicode_ranges.windows(2).all(|pair| pair[0].1 + 1 < pair[1].0));
+        // listing the faces fills the coverage cache the fallback lookups read
+        assert_eq!(font_db.coverage.borrow().len(), 3);
+
+        let second = FontStyles { family: String::from("Second"), size: 40.0, face: FaceSelector::PostScriptName(String::from("Second-Regular")), ..FontStyles::default() };
+        let chain = font_db.fallback_chain(&second);
+        assert!(FontBucket::remove_face(&mut font_db, FaceId(2)));
+        assert!(!font_db.remove_face(FaceId(2)) && !font_db.remove_face(FaceId(7)));
+        assert_eq!(font_db.faces().iter().map(|face| face.id).collect::<Vec<_>>(), vec![FaceId(0), FaceId(1)]);
+        assert_eq!(font_db.buffer_faces().iter().map(|faces| faces.len()).collect::<Vec<_>>(), vec![1, 1]);
+        assert!(!font_db.fallback_chain(&second).contains(&chain[0]));
+        // ids are not reused
+        assert_eq!(font_db.load_font(&font), Ok(vec![FaceId(3)]));
+    }
+
+    #[test]
+    fn font_files() {
+        let woff = include_bytes!("../test/zc2016.woff");
+        let font = crate::woff::decompress_woff(woff).unwrap();
+        let dir = std::env::temp_dir().join(format!("painter_font_files_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("nested")).unwrap();
+        std::fs::write(dir.join("zc2016.WOFF"), woff).unwrap();
+        std::fs::write(dir.join("nested/pair.ttc"), collection(&[font.clone(), renamed(&font, "Second")])).unwrap();
+        std::fs::write(dir.join("nested/broken.ttf"), b"true").unwrap();
+        std::fs::write(dir.join("notes.txt"), b"OTTO").unwrap();
+        #[cfg(unix)]
+        {
+            // a loop back to the directory and a second name for a font file
+            std::os::unix::fs::symlink(&dir, dir.join("nested/loop")).unwrap();
+            std::os::unix::fs::symlink(dir.join("zc2016.WOFF"), dir.join("nested/link.woff")).unwrap();
+        }
+
+        let mut font_db = FontDB::new();
+        assert_eq!(font_db.load_font_file(dir.join("zc2016.WOFF")), Ok(vec![FaceId(0)]));
+        assert_eq!(font_db.load_font_file(dir.join("notes.txt")), Err(FontLoadError::MalformedFont));
+        assert_eq!(font_db.load_font_file(dir.join("missing.ttf")), Err(FontLoadError::Io(std::io::ErrorKind::NotFound)));
+        let ids = font_db.load_fonts_dir(&dir);
+        let expected = if cfg!(unix) { 4 } else { 3 };
+        assert_eq!(ids.len(), expected);
+        assert!((1..=expected as u32).all(|id| ids.contains(&FaceId(id))));
+        let buffers: Vec<Vec<u32>> = font_db.buffer_faces().iter().map(|faces| faces.iter().map(|face| face.index).collect()).collect();
+        assert!(buffers.contains(&vec![0, 1]) && buffers.len() == expected);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // generic families resolve to the configured family, ahead of the fallback faces
+        let cursive = FontStyles { family: String::from("cursive"), size: 40.0, ..FontStyles::default() };
+        let family = |font_db: &FontDB| font_db.db.face(font_db.fallback_chain(&cursive)[0]).unwrap().family.clone();
+        assert_eq!(family(&font_db), "HappyZcool-2016");
+        assert!(font_db.set_generic_family("cursive", "Second"));
+        assert!(!font_db.set_generic_family("emoji", "Second"));
+        assert_eq!(family(&font_db), "Second");
     }
 }
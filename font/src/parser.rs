@@ -2,7 +2,11 @@ use std::convert::TryFrom;
 use std::num::NonZeroU16;
 
 use fontdb::{ID, Database, Query, Family};
-use ttf_parser::{Rect, Style};
+use ttf_parser::{Face, GlyphId, Rect, Style, Tag, VariationAxis};
+use painter_core::font::{ColorGlyph, FontVariation};
+use painter_core::font::shorthand::split_families;
+use painter_core::image::Image;
+use painter_core::paint::color::Color;
 use painter_core::transform::Transform;
 
 use crate::painter_core::path::PathData;
@@ -12,6 +16,8 @@ use crate::painter_core::path::BoundingBox;
 pub struct Glyph {
     family: String,
 
+    id: u16,
+
     units_per_em: NonZeroU16,
 
     // All values below are in font units.
@@ -42,12 +48,27 @@ pub struct Glyph {
     bbox: BoundingBox,
 
     path: PathData,
+
+    /// COLR layers in font units, a `None` color is the text color.
+    layers: Vec<(PathData, Option<Color>)>,
+    raster: Option<RasterGlyph>,
+}
+
+/// An sbix or CBDT bitmap glyph, the offset to its bottom left corner is in strike pixels.
+#[derive(Debug, Clone)]
+struct RasterGlyph {
+    /// The encoded image, decoded only when the glyph is drawn.
+    data: Vec<u8>,
+    x: f32,
+    y: f32,
+    pixels_per_em: f32,
 }
 
 impl Default for Glyph {
     fn default() -> Self {
         Glyph {
             family: "".to_string(),
+            id: 0,
             units_per_em: NonZeroU16::new(1000u16).unwrap(),
             ascent: 0,
             descent: 0,
@@ -65,123 +86,116 @@ impl Default for Glyph {
             style: Default::default(),
             bbox: BoundingBox::new(0.0, 0.0),
             path: Default::default(),
+            layers: vec![],
+            raster: None,
         }
     }
 }
 
 
+/// Glyph lookups on a font database.
+///
+/// `glyph` and `find_font_id` pick the regular face of a family, `FontDB::fallback_chain`
+/// matches the weight, style and stretch of a `FontStyles`. `face_glyph_at` outlines a glyph of
+/// a variable face at the variation coordinates, axes the face doesn't have are ignored.
+/// `face_glyphs_at` and `glyph_indices` do the same for a whole run, parsing the face once.
 pub trait GlyphExt {
     fn glyph<P, T>(&self, family: &str, c: char, f: P) -> Option<T> where P: FnOnce(Glyph) -> T;
+    fn face_glyph<P, T>(&self, id: ID, glyph_id: u16, f: P) -> Option<T> where P: FnOnce(Glyph) -> T;
+    fn face_glyph_at<P, T>(&self, id: ID, glyph_id: u16, variations: &[FontVariation], f: P) -> Option<T> where P: FnOnce(Glyph) -> T;
+    fn face_glyphs_at(&self, id: ID, glyph_ids: &[u16], variations: &[FontVariation]) -> Option<Vec<Glyph>>;
+    fn variation_axes(&self, id: ID) -> Vec<VariationAxis>;
+    fn coverage(&self, id: ID) -> Vec<(u32, u32)>;
+    fn glyph_count(&self, id: ID) -> u16;
+    fn glyph_index(&self, id: ID, c: char) -> Option<u16>;
+    fn glyph_indices(&self, id: ID, text: &str) -> Vec<u16>;
     fn find_font_id(&self, family: &str) -> Option<ID>;
 }
 
 impl GlyphExt for Database {
     fn glyph<P, T>(&self, family: &str, c: char, f: P) -> Option<T> where P: FnOnce(Glyph) -> T {
-        let q = Query {
-            families: &[Family::Name(family)],
-            weight: Default::default(),
-            stretch: Default::default(),
-            style: Default::default(),
-        };
-        let id = self.query(&q)?;
+        let id = self.find_font_id(family)?;
+        let glyph_id = self.glyph_index(id, c)?;
+        self.face_glyph(id, glyph_id, f)
+    }
+
+    fn face_glyph<P, T>(&self, id: ID, glyph_id: u16, f: P) -> Option<T> where P: FnOnce(Glyph) -> T {
+        self.face_glyph_at(id, glyph_id, &[], f)
+    }
+
+    fn face_glyph_at<P, T>(&self, id: ID, glyph_id: u16, variations: &[FontVariation], f: P) -> Option<T> where P: FnOnce(Glyph) -> T {
+        let family = self.face(id)?.family.clone();
+        self.with_face_data(id, |data, face_index| {
+            let mut font = ttf_parser::Face::parse(data, face_index).ok()?;
+            for variation in variations {
+                font.set_variation(Tag::from_bytes(&variation.tag), variation.value);
+            }
+            build_glyph(&font, &family, GlyphId(glyph_id)).map(f)
+        })?
+    }
+
+    fn face_glyphs_at(&self, id: ID, glyph_ids: &[u16], variations: &[FontVariation]) -> Option<Vec<Glyph>> {
+        let family = self.face(id)?.family.clone();
+        self.with_face_data(id, |data, face_index| {
+            let mut font = ttf_parser::Face::parse(data, face_index).ok()?;
+            for variation in variations {
+                font.set_variation(Tag::from_bytes(&variation.tag), variation.value);
+            }
+            glyph_ids.iter().map(|glyph_id| build_glyph(&font, &family, GlyphId(*glyph_id))).collect()
+        })?
+    }
+
+    fn variation_axes(&self, id: ID) -> Vec<VariationAxis> {
         self.with_face_data(id, |data, face_index| {
             let font = ttf_parser::Face::parse(data, face_index).ok()?;
-            let glyph_id = font.glyph_index(c)?;
-            let pixels_per_em = font.units_per_em();
-            let mut builder = PathBuilder { path: PathData::new() };
-            font.outline_glyph(glyph_id, &mut builder);
-            // todo: raster_image_impl
-            let raster_image = font.glyph_raster_image(glyph_id, pixels_per_em);
-            let hor_side_bearing = font.glyph_hor_side_bearing(glyph_id).unwrap_or(0i16);
-            let ver_side_bearing = font.glyph_ver_side_bearing(glyph_id).unwrap_or(0i16);
-            let hor_advance = font.glyph_hor_advance(glyph_id).unwrap_or(0u16);
-            let ver_advance = font.glyph_ver_advance(glyph_id).unwrap_or(0u16);
-            let italic_angle = font.italic_angle();
-            let style = font.style();
-            let bbox = font.glyph_bounding_box(glyph_id).and_then(|rect| {
-                let Rect { x_min: x1, y_min: y1, x_max: x2, y_max: y2 } = rect;
-                Some(BoundingBox {
-                    x1: x1 as f32,
-                    y1: y2 as f32,
-                    x2: x2 as f32,
-                    y2: y2 as f32,
-                })
-            }).unwrap_or(BoundingBox::new(0.0, 0.0));
-
-            let units_per_em = NonZeroU16::new(font.units_per_em())?;
-
-            let ascent = font.ascender();
-            let descent = font.descender();
-
-            let x_height = font.x_height().and_then(|x| u16::try_from(x).ok()).and_then(NonZeroU16::new);
-            let x_height = match x_height {
-                Some(height) => height,
-                None => {
-                    // If not set - fallback to height * 45%.
-                    // 45% is what Firefox uses.
-                    u16::try_from((f32::from(ascent - descent) * 0.45) as i32).ok()
-                        .and_then(NonZeroU16::new)?
-                }
-            };
-
-            let line_through = font.strikeout_metrics();
-            let line_through_position = match line_through {
-                Some(metrics) => metrics.position,
-                None => x_height.get() as i16 / 2,
-            };
-
-            let (underline_position, underline_thickness) = match font.underline_metrics() {
-                Some(metrics) => {
-                    let thickness = u16::try_from(metrics.thickness).ok()
-                        .and_then(NonZeroU16::new)
-                        // `ttf_parser` guarantees that units_per_em is >= 16
-                        .unwrap_or_else(|| NonZeroU16::new(units_per_em.get() / 12).unwrap());
-
-                    (metrics.position, thickness)
+            Some(font.variation_axes().into_iter().collect())
+        }).flatten().unwrap_or_default()
+    }
+
+    /// Returns the char ranges the unicode cmap subtables map.
+    fn coverage(&self, id: ID) -> Vec<(u32, u32)> {
+        self.with_face_data(id, |data, face_index| {
+            let font = ttf_parser::Face::parse(data, face_index).ok()?;
+            let mut chars = vec![];
+            for subtable in font.tables().cmap?.subtables {
+                if subtable.is_unicode() {
+                    subtable.codepoints(|c| chars.push(c));
                 }
-                None => {
-                    (
-                        -(units_per_em.get() as i16) / 9,
-                        NonZeroU16::new(units_per_em.get() / 12).unwrap(),
-                    )
+            }
+            chars.sort_unstable();
+            chars.dedup();
+            let mut ranges: Vec<(u32, u32)> = vec![];
+            for c in chars {
+                match ranges.last_mut() {
+                    Some((_, end)) if *end + 1 == c => *end = c,
+                    _ => ranges.push((c, c)),
                 }
-            };
-
-            // 0.2 and 0.4 are generic offsets used by some applications (Inkscape/librsvg).
-            let mut subscript_offset = (units_per_em.get() as f32 / 0.2).round() as i16;
-            let mut superscript_offset = (units_per_em.get() as f32 / 0.4).round() as i16;
-            if let Some(metrics) = font.subscript_metrics() {
-                subscript_offset = metrics.y_offset;
             }
+            Some(ranges)
+        }).flatten().unwrap_or_default()
+    }
 
-            if let Some(metrics) = font.superscript_metrics() {
-                superscript_offset = metrics.y_offset;
-            }
+    fn glyph_count(&self, id: ID) -> u16 {
+        self.with_face_data(id, |data, face_index| {
+            ttf_parser::Face::parse(data, face_index).ok().map(|font| font.number_of_glyphs())
+        }).flatten().unwrap_or_default()
+    }
 
-            let path = builder.path;
-            Some(f(Glyph {
-                family: String::from(family),
-                units_per_em,
-                ascent,
-                descent,
-                x_height,
-                underline_position,
-                underline_thickness,
-                line_through_position,
-                subscript_offset,
-                superscript_offset,
-                hor_side_bearing,
-                ver_side_bearing,
-                hor_advance,
-                ver_advance,
-                italic_angle,
-                style,
-                bbox,
-                path,
-            }))
+    fn glyph_index(&self, id: ID, c: char) -> Option<u16> {
+        self.with_face_data(id, |data, face_index| {
+            let font = ttf_parser::Face::parse(data, face_index).ok()?;
+            font.glyph_index(c).map(|glyph_id| glyph_id.0)
         })?
     }
 
+    /// Maps every char of the text, missing chars get the `.notdef` glyph 0.
+    fn glyph_indices(&self, id: ID, text: &str) -> Vec<u16> {
+        self.with_face_data(id, |data, face_index| {
+            let font = ttf_parser::Face::parse(data, face_index).ok()?;
+            Some(text.chars().map(|c| font.glyph_index(c).map_or(0, |glyph_id| glyph_id.0)).collect())
+        }).flatten().unwrap_or_else(|| vec![0; text.chars().count()])
+    }
+
     fn find_font_id(&self, family: &str) -> Option<ID> {
         let q = Query {
             families: &[Family::Name(family)],
@@ -193,7 +207,214 @@ impl GlyphExt for Database {
     }
 }
 
+/// Splits a css `font-family` list, quoted names are never generic families.
+pub fn parse_families(list: &str) -> Vec<Family<'_>> {
+    split_families(list).unwrap_or_default().into_iter().filter_map(|(name, quoted)| {
+        match name {
+            _ if quoted => Some(Family::Name(name)),
+            "" => None,
+            "serif" => Some(Family::Serif),
+            "sans-serif" => Some(Family::SansSerif),
+            "cursive" => Some(Family::Cursive),
+            "fantasy" => Some(Family::Fantasy),
+            "monospace" => Some(Family::Monospace),
+            _ => Some(Family::Name(name)),
+        }
+    }).collect()
+}
+
+fn build_glyph(font: &Face, family: &str, glyph_id: GlyphId) -> Option<Glyph> {
+    let pixels_per_em = font.units_per_em();
+    let mut builder = PathBuilder { path: PathData::new() };
+    font.outline_glyph(glyph_id, &mut builder);
+    // the largest strike, it is scaled down to the font size
+    let raster = font.glyph_raster_image(glyph_id, pixels_per_em).map(|image| RasterGlyph {
+        data: image.data.to_vec(),
+        x: image.x as f32,
+        y: image.y as f32,
+        pixels_per_em: image.pixels_per_em.max(1) as f32,
+    });
+    let layers = color_layers(font, glyph_id).unwrap_or_default();
+    let hor_side_bearing = font.glyph_hor_side_bearing(glyph_id).unwrap_or(0i16);
+    let ver_side_bearing = font.glyph_ver_side_bearing(glyph_id).unwrap_or(0i16);
+    let hor_advance = font.glyph_hor_advance(glyph_id).unwrap_or(0u16);
+    let ver_advance = font.glyph_ver_advance(glyph_id).unwrap_or(0u16);
+    let italic_angle = font.italic_angle();
+    let style = font.style();
+    let bbox = font.glyph_bounding_box(glyph_id).map(|rect| {
+        let Rect { x_min: x1, y_min: y1, x_max: x2, y_max: y2 } = rect;
+        BoundingBox {
+            x1: x1 as f32,
+            y1: y1 as f32,
+            x2: x2 as f32,
+            y2: y2 as f32,
+        }
+    }).unwrap_or(BoundingBox::new(0.0, 0.0));
+
+    let units_per_em = NonZeroU16::new(font.units_per_em())?;
+
+    let ascent = font.ascender();
+    let descent = font.descender();
+
+    let x_height = font.x_height().and_then(|x| u16::try_from(x).ok()).and_then(NonZeroU16::new);
+    let x_height = match x_height {
+        Some(height) => height,
+        None => {
+            // If not set - fallback to height * 45%.
+            // 45% is what Firefox uses.
+            u16::try_from((f32::from(ascent - descent) * 0.45) as i32).ok()
+                .and_then(NonZeroU16::new)?
+        }
+    };
+
+    let line_through = font.strikeout_metrics();
+    let line_through_position = match line_through {
+        Some(metrics) => metrics.position,
+        None => x_height.get() as i16 / 2,
+    };
+
+    let (underline_position, underline_thickness) = match font.underline_metrics() {
+        Some(metrics) => {
+            let thickness = u16::try_from(metrics.thickness).ok()
+                .and_then(NonZeroU16::new)
+                // `ttf_parser` guarantees that units_per_em is >= 16
+                .unwrap_or_else(|| NonZeroU16::new(units_per_em.get() / 12).unwrap());
+
+            (metrics.position, thickness)
+        }
+        None => {
+            (
+                -(units_per_em.get() as i16) / 9,
+                NonZeroU16::new(units_per_em.get() / 12).unwrap(),
+            )
+        }
+    };
+
+    // 0.2 and 0.4 are generic offsets used by some applications (Inkscape/librsvg).
+    let mut subscript_offset = (units_per_em.get() as f32 / 0.2).round() as i16;
+    let mut superscript_offset = (units_per_em.get() as f32 / 0.4).round() as i16;
+    if let Some(metrics) = font.subscript_metrics() {
+        subscript_offset = metrics.y_offset;
+    }
+
+    if let Some(metrics) = font.superscript_metrics() {
+        superscript_offset = metrics.y_offset;
+    }
+
+    let path = builder.path;
+    Some(Glyph {
+        family: String::from(family),
+        id: glyph_id.0,
+        units_per_em,
+        ascent,
+        descent,
+        x_height,
+        underline_position,
+        underline_thickness,
+        line_through_position,
+        subscript_offset,
+        superscript_offset,
+        hor_side_bearing,
+        ver_side_bearing,
+        hor_advance,
+        ver_advance,
+        italic_angle,
+        style,
+        bbox,
+        path,
+        layers,
+        raster,
+    })
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let r = data.get(offset..offset + 2)?;
+    Some(u16::from_be_bytes([r[0], r[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let r = data.get(offset..offset + 4)?;
+    Some(u32::from_be_bytes([r[0], r[1], r[2], r[3]]))
+}
+
+/// Reads the COLRv0 layers of a glyph, colored from the first CPAL palette.
+fn color_layers(font: &Face, glyph_id: GlyphId) -> Option<Vec<(PathData, Option<Color>)>> {
+    let colr = font.raw_face().table(Tag::from_bytes(b"COLR"))?;
+    let cpal = font.raw_face().table(Tag::from_bytes(b"CPAL"));
+    let num_base_glyphs = read_u16(colr, 2)? as usize;
+    let base_glyphs = read_u32(colr, 4)? as usize;
+    let layer_records = read_u32(colr, 8)? as usize;
+    let num_layers = read_u16(colr, 12)? as usize;
+
+    // the base glyph records are sorted by glyph id
+    let (mut low, mut high) = (0, num_base_glyphs);
+    let record = loop {
+        if low >= high {
+            return None;
+        }
+        let middle = (low + high) / 2;
+        let record = base_glyphs + middle * 6;
+        match read_u16(colr, record)?.cmp(&glyph_id.0) {
+            std::cmp::Ordering::Less => low = middle + 1,
+            std::cmp::Ordering::Greater => high = middle,
+            std::cmp::Ordering::Equal => break record,
+        }
+    };
+    let first = read_u16(colr, record + 2)? as usize;
+    let count = read_u16(colr, record + 4)? as usize;
+    if first + count > num_layers {
+        return None;
+    }
+    (first..first + count).map(|index| {
+        let layer = layer_records + index * 4;
+        let mut builder = PathBuilder { path: PathData::new() };
+        font.outline_glyph(GlyphId(read_u16(colr, layer)?), &mut builder);
+        let color = match read_u16(colr, layer + 2)? {
+            0xffff => None,
+            index => cpal.and_then(|cpal| palette_color(cpal, index)),
+        };
+        Some((builder.path, color))
+    }).collect()
+}
+
+/// Returns a color of the first CPAL palette, records are stored as BGRA.
+fn palette_color(cpal: &[u8], index: u16) -> Option<Color> {
+    let num_entries = read_u16(cpal, 2)?;
+    if index >= num_entries {
+        return None;
+    }
+    let color_records = read_u32(cpal, 8)? as usize;
+    let first = read_u16(cpal, 12)? as usize;
+    let record = cpal.get(color_records + (first + index as usize) * 4..)?.get(..4)?;
+    Some(Color::from_rgba8(record[2], record[1], record[0], record[3]))
+}
+
+/// The slant of a synthetic oblique, the css default oblique angle.
+const SYNTHETIC_OBLIQUE_ANGLE: f32 = 14.0;
+
 impl Glyph {
+    /// Fakes a bold or oblique version of the glyph, for faces missing the requested weight or style.
+    ///
+    /// The outline grows by 1/24 em and the advance is kept, like browsers do.
+    pub fn synthesize(&mut self, bold: bool, oblique: bool) {
+        let strength = self.units_per_em.get() as f32 / 48.0;
+        for path in std::iter::once(&mut self.path).chain(self.layers.iter_mut().map(|(path, _)| path)) {
+            if bold {
+                *path = path.embolden(strength);
+            }
+            if oblique {
+                // font units grow upwards, so a positive skew slants the glyph to the right
+                path.transform(Transform::new_skew_x(SYNTHETIC_OBLIQUE_ANGLE));
+            }
+        }
+    }
+
+    /// The glyph index in its font face.
+    #[inline]
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+
     #[inline]
     pub fn scale(&self, font_size: f32) -> f32 {
         font_size / self.units_per_em.get() as f32
@@ -251,6 +472,24 @@ impl Glyph {
         self.path.transform_to(ts)
     }
 
+    /// The colored parts of the glyph at the font size, relative to the glyph origin like `path`.
+    ///
+    /// A bitmap wins over COLR layers, empty when the glyph has neither or the bitmap can't be decoded.
+    pub fn color_parts(&self, font_size: f32) -> Vec<ColorGlyph> {
+        if let Some((raster, image)) = self.raster.as_ref().and_then(|raster| Image::decode(&raster.data).map(|image| (raster, image))) {
+            let scale = font_size / raster.pixels_per_em;
+            // image rows go down from the top left corner
+            let mut transform = Transform::new_translate(raster.x * scale, -(raster.y + image.height() as f32) * scale);
+            transform.scale(scale, scale);
+            return vec![ColorGlyph::Image { image, transform }];
+        }
+        let scale = self.scale(font_size);
+        self.layers.iter().map(|(path, color)| ColorGlyph::Layer {
+            path: path.transform_to(Transform::new_scale(scale, -scale)),
+            color: *color,
+        }).collect()
+    }
+
     pub fn advance_width(&self, font_size: f32) -> f32 {
         self.hor_advance as f32 * self.scale(font_size)
     }
@@ -305,7 +544,7 @@ impl ttf_parser::OutlineBuilder for PathBuilder {
 #[cfg(test)]
 mod test {
     use fontdb::{Database, Family, Query};
-    use crate::parser::GlyphExt;
+    use crate::parser::{GlyphExt, parse_families};
 
     #[test]
     fn font_build() {
@@ -330,4 +569,12 @@ mod test {
         let g = db.glyph("HappyZcool-2016", "家".chars().next().unwrap(), |g| g);
         println!("{:?}", g)
     }
+
+    #[test]
+    fn families() {
+        let families = parse_families(r#"Foo Bar, "serif", 'Baz' ,sans-serif,, monospace"#);
+        assert_eq!(families, vec![Family::Name("Foo Bar"), Family::Name("serif"), Family::Name("Baz"), Family::SansSerif, Family::Monospace]);
+        assert!(parse_families("").is_empty());
+        assert_eq!(parse_families(r#"'Foo, Bar', serif"#), vec![Family::Name("Foo, Bar"), Family::Serif]);
+    }
 }
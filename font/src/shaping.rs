@@ -0,0 +1,42 @@
+use fontdb::{Database, ID};
+use rustybuzz::{Direction, Feature, Tag, UnicodeBuffer, Variation};
+use painter_core::font::{FontKerning, FontStyles, FontVariation, PositionedGlyph};
+
+use crate::parser::{Glyph, GlyphExt};
+
+/// Shapes a piece of a single direction with the face `id` at the variation coordinates, applying
+/// the style kerning and feature settings. The glyphs come in visual order, missing chars get `.notdef`.
+pub(crate) fn shape(db: &Database, id: ID, style: &FontStyles, variations: &[FontVariation], text: &str, rtl: bool) -> Option<Vec<(Glyph, PositionedGlyph)>> {
+    let mut features: Vec<Feature> = style.features.iter()
+        .map(|feature| Feature::new(Tag::from_bytes(&feature.tag), feature.value, ..))
+        .collect();
+    match style.kerning {
+        FontKerning::Auto => {}
+        FontKerning::Normal => features.push(Feature::new(Tag::from_bytes(b"kern"), 1, ..)),
+        FontKerning::None => features.push(Feature::new(Tag::from_bytes(b"kern"), 0, ..)),
+    }
+
+    let shaped: Vec<(u16, f32, f32, f32)> = db.with_face_data(id, |data, face_index| {
+        let mut face = rustybuzz::Face::from_slice(data, face_index)?;
+        face.set_variations(&variations.iter().map(|variation| Variation { tag: Tag::from_bytes(&variation.tag), value: variation.value }).collect::<Vec<_>>());
+        let mut buffer = UnicodeBuffer::new();
+        buffer.push_str(text);
+        buffer.set_direction(if rtl { Direction::RightToLeft } else { Direction::LeftToRight });
+        let output = rustybuzz::shape(&face, &features, buffer);
+        let scale = style.size / face.units_per_em() as f32;
+        let list = output.glyph_infos().iter().zip(output.glyph_positions()).map(|(info, position)| {
+            (info.glyph_id as u16, position.x_advance as f32 * scale, position.x_offset as f32 * scale, position.y_offset as f32 * scale)
+        }).collect();
+        Some(list)
+    })??;
+
+    let glyph_ids: Vec<u16> = shaped.iter().map(|(glyph_id, ..)| *glyph_id).collect();
+    let glyphs = db.face_glyphs_at(id, &glyph_ids, variations)?;
+    let mut x = 0.0;
+    Some(glyphs.into_iter().zip(shaped).map(|(glyph, (glyph_id, advance, x_offset, y_offset))| {
+        // shaping offsets grow upwards
+        let position = PositionedGlyph { glyph_id, x: x + x_offset, y: -y_offset, advance };
+        x += advance;
+        (glyph, position)
+    }).collect())
+}
@@ -1,6 +1,7 @@
 extern crate miniz_oxide;
 
 use std::ops::{Deref, DerefMut};
+use painter_core::font::FontLoadError;
 
 #[derive(Debug, Clone)]
 struct WOFFHeader {
@@ -93,8 +94,8 @@ impl<'a> ReadStream<'a> {
 
     pub fn read(&mut self, step: usize) -> Option<&[u8]> {
         let start = self.offset;
-        self.offset += step;
-        let end = self.offset;
+        let end = start.checked_add(step)?;
+        self.offset = end;
 
         self.data.get(start..end)
     }
@@ -181,28 +182,56 @@ fn convert_be_to_u32(f: Vec<u8>) -> u32 {
     u32::from_be_bytes([*n0, *n1, *n2, *n3])
 }
 
-pub fn decompress_woff(input: &[u8]) -> Option<Vec<u8>> {
+/// The sfnt checksum, the sum of the data as big endian u32 words padded with zeros.
+pub(crate) fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0u8; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+/// The checksum of a table, the `checkSumAdjustment` of `head` counts as zero.
+pub(crate) fn table_checksum(tag: &[u8], data: &[u8]) -> u32 {
+    let sum = checksum(data);
+    match data.get(8..12) {
+        Some(adjustment) if tag == b"head" => sum.wrapping_sub(convert_be_to_u32(adjustment.to_vec())),
+        _ => sum,
+    }
+}
+
+pub fn decompress_woff(input: &[u8]) -> Result<Vec<u8>, FontLoadError> {
     let mut output = Vec::<u8>::new();
     let mut ws = WriteStream::new(&mut output);
     let mut rs = ReadStream::new(input);
-    let header: WOFFHeader = WOFFHeader::new(&mut rs)?;
+    let header: WOFFHeader = WOFFHeader::new(&mut rs).ok_or(FontLoadError::TruncatedHeader)?;
+    if header.signature != b"wOFF" {
+        return Err(FontLoadError::BadSignature);
+    }
+    if convert_be_to_u32(header.length.clone()) as usize != input.len() {
+        return Err(FontLoadError::SizeMismatch);
+    }
 
     ws.write(header.flavor);
     ws.write(header.num_tables.clone());
 
     let num_tables: u16 = convert_be_to_u16(header.num_tables);
-    let mut temp = num_tables.clone();
+    // the sfnt range shift would not fit in a u16
+    if num_tables == 0 || num_tables > 4095 {
+        return Err(FontLoadError::SizeMismatch);
+    }
+    let mut temp = num_tables;
     let mut entry_selector = 0u16;
     let mut search_range = 16u16;
 
     loop {
         if temp <= 1 { break; }
-        temp = temp >> 1;
+        temp >>= 1;
         entry_selector += 1;
-        search_range = search_range << 1;
+        search_range <<= 1;
     }
 
-    let range_shift = convert_u16_to_u8s_be(num_tables.clone() * 16 - search_range);
+    let range_shift = convert_u16_to_u8s_be(num_tables * 16 - search_range);
     let search_range = convert_u16_to_u8s_be(search_range);
     let entry_selector = convert_u16_to_u8s_be(entry_selector);
 
@@ -212,70 +241,81 @@ pub fn decompress_woff(input: &[u8]) -> Option<Vec<u8>> {
 
     let mut table_directory_map = Vec::<TableDirectory>::new();
     let mut offset = ws.offset;
-    let mut out_offset_map = Vec::<BufferOffset>::new();
 
-    for _i in 0..num_tables.clone() {
-        let table_directory = TableDirectory::new(&mut rs)?;
+    for _i in 0..num_tables {
+        let table_directory = TableDirectory::new(&mut rs).ok_or(FontLoadError::TruncatedHeader)?;
         table_directory_map.push(table_directory);
         offset += 4 * 4;
     }
 
+    // the tables have to lie after the directory, inside the file and apart from each other
+    let mut ranges = Vec::<(usize, usize)>::new();
+    for table_directory in table_directory_map.iter() {
+        let start = convert_be_to_u32(table_directory.offset.clone()) as usize;
+        let comp_length = convert_be_to_u32(table_directory.comp_length.clone()) as usize;
+        let orig_length = convert_be_to_u32(table_directory.orig_length.clone()) as usize;
+        let end = start.checked_add(comp_length).ok_or(FontLoadError::SizeMismatch)?;
+        if start < rs.offset || end > input.len() || comp_length > orig_length {
+            return Err(FontLoadError::SizeMismatch);
+        }
+        ranges.push((start, end));
+    }
+    ranges.sort();
+    if ranges.windows(2).any(|pair| pair[0].1 > pair[1].0) {
+        return Err(FontLoadError::SizeMismatch);
+    }
+
     for table_directory in table_directory_map.iter() {
         let TableDirectory { tag, offset: _, comp_length: _, orig_length, orig_checksum } = table_directory;
         ws.write(tag.to_vec());
         ws.write(orig_checksum.to_vec());
-        ws.write(BufferOffset(offset.clone()).to_be());
+        ws.write(BufferOffset(offset).to_be());
         ws.write(orig_length.to_vec());
-        out_offset_map.push(BufferOffset(offset.clone()));
         let orig_length = convert_be_to_u32(table_directory.orig_length.clone()) as usize;
-        offset += orig_length;
-        if offset % 4 != 0 {
-            offset += 4 - (offset % 4);
-        }
+        // tables start on 4 byte boundaries
+        offset = offset.checked_add(orig_length)
+            .and_then(|offset| offset.checked_add((4 - offset % 4) % 4))
+            .ok_or(FontLoadError::SizeMismatch)?;
     }
 
-    out_offset_map.reserve(0);
+    if convert_be_to_u32(header.total_sfnt_size) as usize != offset {
+        return Err(FontLoadError::SizeMismatch);
+    }
 
     for table_directory in table_directory_map {
-        let TableDirectory { tag: _, offset, comp_length, orig_length, orig_checksum: _ } = table_directory;
+        let TableDirectory { tag, offset, comp_length, orig_length, orig_checksum } = table_directory;
         let comp_length = convert_be_to_u32(comp_length.to_vec()) as usize;
         let orig_length = convert_be_to_u32(orig_length.to_vec()) as usize;
         rs.offset = convert_be_to_u32(offset.to_vec()) as usize;
-        let mut data = rs.read_vec(comp_length)?;
-        if comp_length != orig_length.clone() {
-            data = {
-                let result = miniz_oxide::inflate::decompress_to_vec_zlib(&data);
-                if result.is_ok() {
-                    Some(result.unwrap())
-                } else {
-                    None
-                }
-            }?;
+        let mut data = rs.read_vec(comp_length).ok_or(FontLoadError::SizeMismatch)?;
+        if comp_length != orig_length {
+            data = miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(&data, orig_length)
+                .map_err(|_| FontLoadError::DecompressionFailed)?;
+        }
+        if data.len() != orig_length {
+            return Err(FontLoadError::SizeMismatch);
+        }
+        if table_checksum(&tag, &data) != convert_be_to_u32(orig_checksum) {
+            return Err(FontLoadError::ChecksumMismatch);
         }
-        let out_offset = out_offset_map.pop().unwrap();
-        ws.offset = out_offset.0.clone();
         ws.write(data);
-        let offset = out_offset.0 + orig_length;
 
         let mut padding = 0usize;
 
-        if offset % 4 != 0 {
-            padding = 4 - offset % 4
-        }
-        let mut padding_vec = Vec::<u8>::new();
-        for _ in 0..padding {
-            padding_vec.push(0u8);
+        if !orig_length.is_multiple_of(4) {
+            padding = 4 - orig_length % 4
         }
-
-        ws.write(padding_vec);
+        ws.write(vec![0u8; padding]);
     }
 
-    Some(output)
+    Ok(output)
 }
 
 #[cfg(test)]
 mod test {
     use crate::woff::decompress_woff;
+    use crate::FontDB;
+    use painter_core::font::FontLoadError;
     use std::fs::File;
     use std::io::Write;
 
@@ -288,4 +328,38 @@ mod test {
         file.write(result.as_slice()).unwrap();
         println!("ok, {:?}", result.len());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn errors() {
+        let file = include_bytes!("../test/zc2016.woff") as &[u8];
+        let entry = |index: usize, field: usize| 44 + index * 20 + field * 4;
+        let read = |data: &[u8], offset: usize| u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap()) as usize;
+        let patch = |offset: usize, value: u32| {
+            let mut data = file.to_vec();
+            data[offset..offset + 4].copy_from_slice(&value.to_be_bytes());
+            data
+        };
+
+        let mut data = file.to_vec();
+        data[3] = b'2';
+        assert_eq!(decompress_woff(&data), Err(FontLoadError::BadSignature));
+        assert_eq!(decompress_woff(&file[..40]), Err(FontLoadError::TruncatedHeader));
+        assert_eq!(decompress_woff(&file[..file.len() - 1]), Err(FontLoadError::SizeMismatch));
+        assert_eq!(decompress_woff(&patch(entry(0, 4), 0)), Err(FontLoadError::ChecksumMismatch));
+        assert_eq!(decompress_woff(&patch(entry(1, 1), read(file, entry(0, 1)) as u32)), Err(FontLoadError::SizeMismatch));
+        assert_eq!(decompress_woff(&patch(entry(0, 3), u32::MAX)), Err(FontLoadError::SizeMismatch));
+        assert_eq!(decompress_woff(&patch(entry(0, 1), u32::MAX)), Err(FontLoadError::SizeMismatch));
+
+        let compressed = (0..).find(|index| read(file, entry(*index, 2)) < read(file, entry(*index, 3))).unwrap();
+        let mut data = file.to_vec();
+        let offset = read(file, entry(compressed, 1));
+        data[offset..offset + 2].copy_from_slice(&[0xff, 0xff]);
+        assert_eq!(decompress_woff(&data), Err(FontLoadError::DecompressionFailed));
+
+        let mut db = FontDB::new();
+        assert_eq!(db.load_font(&patch(4, 0x74727565)), Err(FontLoadError::UnsupportedFlavor));
+        assert_eq!(db.load_font(b"\0\x01\0\0"), Err(FontLoadError::MalformedFont));
+        assert_eq!(db.load_font(b"GIF89a"), Err(FontLoadError::BadSignature));
+        assert!(db.load_font(file).is_ok());
+    }
+}
@@ -0,0 +1,739 @@
+extern crate brotli_decompressor;
+
+use std::io::Read;
+use painter_core::font::FontLoadError;
+use crate::woff::checksum;
+
+// wOF2
+const SIGNATURE: u32 = 0x774f4632;
+// ttcf
+const FLAVOR_COLLECTION: u32 = 0x74746366;
+/// Upper bound of the decompressed stream, the declared sizes are not trusted to limit brotli expansion.
+const MAX_STREAM_LENGTH: usize = 64 << 20;
+
+/// Tags of the table directory flags, 63 means the tag follows the flags.
+const KNOWN_TAGS: [&[u8; 4]; 63] = [
+    b"cmap", b"head", b"hhea", b"hmtx", b"maxp", b"name", b"OS/2", b"post", b"cvt ", b"fpgm", b"glyf",
+    b"loca", b"prep", b"CFF ", b"VORG", b"EBDT", b"EBLC", b"gasp", b"hdmx", b"kern", b"LTSH", b"PCLT",
+    b"VDMX", b"vhea", b"vmtx", b"BASE", b"GDEF", b"GPOS", b"GSUB", b"EBSC", b"JSTF", b"MATH", b"CBDT",
+    b"CBLC", b"COLR", b"CPAL", b"SVG ", b"sbix", b"acnt", b"avar", b"bdat", b"bloc", b"bsln", b"cvar",
+    b"fdsc", b"feat", b"fmtx", b"fvar", b"gvar", b"hsty", b"just", b"lcar", b"mort", b"morx", b"opbd",
+    b"prop", b"trak", b"Zapf", b"Silf", b"Glat", b"Gloc", b"Feat", b"Sill",
+];
+
+// simple glyph flags
+const ON_CURVE_POINT: u8 = 0x01;
+const X_SHORT_VECTOR: u8 = 0x02;
+const Y_SHORT_VECTOR: u8 = 0x04;
+const X_IS_SAME_OR_POSITIVE: u8 = 0x10;
+const Y_IS_SAME_OR_POSITIVE: u8 = 0x20;
+const OVERLAP_SIMPLE: u8 = 0x40;
+
+// composite glyph flags
+const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+const WE_HAVE_A_SCALE: u16 = 0x0008;
+const MORE_COMPONENTS: u16 = 0x0020;
+const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+const WE_HAVE_INSTRUCTIONS: u16 = 0x0100;
+
+struct Reader<'a> {
+    offset: usize,
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader { offset: 0, data }
+    }
+
+    fn read(&mut self, len: usize) -> Option<&'a [u8]> {
+        let data = self.data.get(self.offset..self.offset.checked_add(len)?)?;
+        self.offset += len;
+        Some(data)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.read(1)?[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        let r = self.read(2)?;
+        Some(u16::from_be_bytes([r[0], r[1]]))
+    }
+
+    fn i16(&mut self) -> Option<i16> {
+        Some(self.u16()? as i16)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        let r = self.read(4)?;
+        Some(u32::from_be_bytes([r[0], r[1], r[2], r[3]]))
+    }
+
+    /// Reads a `UIntBase128`, 7 bits per byte with the high bit set on all but the last byte.
+    fn base128(&mut self) -> Option<u32> {
+        let mut value = 0u32;
+        for i in 0..5 {
+            let byte = self.u8()?;
+            // leading zeros and overflows are invalid
+            if (i == 0 && byte == 0x80) || value & 0xfe00_0000 != 0 {
+                return None;
+            }
+            value = (value << 7) | (byte & 0x7f) as u32;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    /// Reads a `255UInt16`.
+    fn u255_16(&mut self) -> Option<u16> {
+        match self.u8()? {
+            253 => self.u16(),
+            254 => Some(self.u8()? as u16 + 506),
+            255 => Some(self.u8()? as u16 + 253),
+            code => Some(code as u16),
+        }
+    }
+}
+
+struct TableEntry {
+    tag: [u8; 4],
+    transformed: bool,
+    /// The length of the table in the decompressed stream.
+    length: usize,
+}
+
+/// Decodes a WOFF2 font into an sfnt font, see https://www.w3.org/TR/WOFF2/.
+///
+/// Transformed `glyf`, `loca` and `hmtx` tables are reconstructed, font collections are not supported.
+pub fn decompress_woff2(input: &[u8]) -> Result<Vec<u8>, FontLoadError> {
+    let mut rs = Reader::new(input);
+    if rs.u32().ok_or(FontLoadError::TruncatedHeader)? != SIGNATURE {
+        return Err(FontLoadError::BadSignature);
+    }
+    let (entries, total_compressed_size) = read_header(&mut rs).ok_or(FontLoadError::TruncatedHeader)?;
+    let flavor = Reader { offset: 4, data: input }.u32().ok_or(FontLoadError::TruncatedHeader)?;
+    let length = Reader { offset: 8, data: input }.u32().ok_or(FontLoadError::TruncatedHeader)?;
+    if flavor == FLAVOR_COLLECTION {
+        return Err(FontLoadError::UnsupportedFlavor);
+    }
+    if length as usize != input.len() {
+        return Err(FontLoadError::SizeMismatch);
+    }
+
+    // the loca table is rebuilt from glyf and must not carry any data
+    if entries.iter().any(|entry| &entry.tag == b"loca" && entry.transformed && entry.length != 0) {
+        return Err(FontLoadError::SizeMismatch);
+    }
+    // the stream holds exactly the tables, reading one byte more catches longer streams
+    let stream_length = entries.iter().try_fold(0usize, |sum, entry| sum.checked_add(entry.length))
+        .filter(|length| *length <= MAX_STREAM_LENGTH)
+        .ok_or(FontLoadError::SizeMismatch)?;
+    let compressed = rs.read(total_compressed_size).ok_or(FontLoadError::SizeMismatch)?;
+    let mut stream = vec![];
+    brotli_decompressor::Decompressor::new(compressed, 4096).take(stream_length as u64 + 1).read_to_end(&mut stream)
+        .map_err(|_| FontLoadError::DecompressionFailed)?;
+    if stream.len() != stream_length {
+        return Err(FontLoadError::SizeMismatch);
+    }
+    let mut stream = Reader::new(&stream);
+    let mut tables = vec![];
+    for entry in entries.iter() {
+        tables.push((entry.tag, stream.read(entry.length).ok_or(FontLoadError::SizeMismatch)?.to_vec()));
+    }
+    reconstruct_tables(&entries, &mut tables).ok_or(FontLoadError::MalformedFont)?;
+    Ok(build_sfnt(flavor, tables))
+}
+
+/// Reads the table directory, returns the entries and the compressed stream size.
+fn read_header(rs: &mut Reader) -> Option<(Vec<TableEntry>, usize)> {
+    let _flavor = rs.u32()?;
+    let _length = rs.u32()?;
+    let num_tables = rs.u16()?;
+    let _reserved = rs.u16()?;
+    let _total_sfnt_size = rs.u32()?;
+    let total_compressed_size = rs.u32()? as usize;
+    // major and minor version, metadata and private data blocks
+    rs.read(2 + 2 + 4 * 5)?;
+    // the sfnt range shift would not fit in a u16
+    if num_tables == 0 || num_tables > 4095 {
+        return None;
+    }
+
+    let mut entries = vec![];
+    for _ in 0..num_tables {
+        let flags = rs.u8()?;
+        let tag = match flags & 0x3f {
+            63 => rs.read(4)?.try_into().ok()?,
+            index => *KNOWN_TAGS[index as usize],
+        };
+        let version = flags >> 6;
+        // glyf and loca are transformed by default, other tables by a non null version
+        let transformed = match &tag {
+            b"glyf" | b"loca" => version == 0,
+            _ => version != 0,
+        };
+        let orig_length = rs.base128()? as usize;
+        let length = if transformed { rs.base128()? as usize } else { orig_length };
+        entries.push(TableEntry { tag, transformed, length });
+    }
+    Some((entries, total_compressed_size))
+}
+
+/// Replaces the transformed tables with the rebuilt ones.
+fn reconstruct_tables(entries: &[TableEntry], tables: &mut [([u8; 4], Vec<u8>)]) -> Option<()> {
+    let transformed = |tag: &[u8; 4]| entries.iter().any(|entry| entry.tag == *tag && entry.transformed);
+    let position = |tables: &[([u8; 4], Vec<u8>)], tag: &[u8; 4]| tables.iter().position(|(t, _)| t == tag);
+    let mut x_mins = None;
+    if transformed(b"glyf") {
+        let glyf = position(tables, b"glyf")?;
+        let loca = position(tables, b"loca")?;
+        let (glyf_data, loca_data, mins) = reconstruct_glyf(&tables[glyf].1)?;
+        tables[glyf].1 = glyf_data;
+        tables[loca].1 = loca_data;
+        x_mins = Some(mins);
+    }
+    if transformed(b"hmtx") {
+        let maxp = &tables[position(tables, b"maxp")?].1;
+        let num_glyphs = Reader { offset: 4, data: maxp }.u16()?;
+        let hhea = &tables[position(tables, b"hhea")?].1;
+        let num_h_metrics = Reader { offset: 34, data: hhea }.u16()?;
+        let hmtx = position(tables, b"hmtx")?;
+        // the left side bearings come from the reconstructed glyphs
+        tables[hmtx].1 = reconstruct_hmtx(&tables[hmtx].1, num_glyphs, num_h_metrics, x_mins.as_ref()?)?;
+    }
+    Some(())
+}
+
+/// Rebuilds the `glyf` and `loca` tables, also returns the `xMin` of every glyph.
+fn reconstruct_glyf(data: &[u8]) -> Option<(Vec<u8>, Vec<u8>, Vec<i16>)> {
+    let mut rs = Reader::new(data);
+    let _reserved = rs.u16()?;
+    let option_flags = rs.u16()?;
+    let num_glyphs = rs.u16()? as usize;
+    let index_format = rs.u16()?;
+    let mut sizes = [0usize; 7];
+    for size in sizes.iter_mut() {
+        *size = rs.u32()? as usize;
+    }
+    let mut streams = sizes.iter().map(|size| rs.read(*size).map(Reader::new)).collect::<Option<Vec<_>>>()?.into_iter();
+    let mut n_contours = streams.next()?;
+    let mut n_points = streams.next()?;
+    let mut flags = streams.next()?;
+    let mut glyphs = streams.next()?;
+    let mut composites = streams.next()?;
+    let mut bboxes = streams.next()?;
+    let mut instructions = streams.next()?;
+    let bbox_bitmap = bboxes.read(((num_glyphs + 31) >> 5) << 2)?;
+    let overlap_bitmap = if option_flags & 1 != 0 { Some(rs.read((num_glyphs + 7) >> 3)?) } else { None };
+    let bit = |bitmap: &[u8], index: usize| bitmap[index >> 3] & (0x80 >> (index & 7)) != 0;
+
+    let mut glyf = vec![];
+    let mut loca = vec![];
+    let mut x_mins = vec![];
+    for index in 0..num_glyphs {
+        write_offset(&mut loca, glyf.len(), index_format)?;
+        let contours = n_contours.i16()?;
+        let explicit_bbox = bit(bbox_bitmap, index);
+        if contours == 0 {
+            if explicit_bbox {
+                return None;
+            }
+            x_mins.push(0);
+            continue;
+        }
+        let mut bbox = if explicit_bbox { Some([bboxes.i16()?, bboxes.i16()?, bboxes.i16()?, bboxes.i16()?]) } else { None };
+
+        if contours < 0 {
+            // composite glyphs always store their bounding box
+            let bbox = bbox?;
+            let (length, have_instructions) = composite_length(&composites.data[composites.offset..])?;
+            write_i16s(&mut glyf, &[-1, bbox[0], bbox[1], bbox[2], bbox[3]]);
+            glyf.extend_from_slice(composites.read(length)?);
+            if have_instructions {
+                let length = glyphs.u255_16()?;
+                glyf.extend_from_slice(&length.to_be_bytes());
+                glyf.extend_from_slice(instructions.read(length as usize)?);
+            }
+            x_mins.push(bbox[0]);
+        } else {
+            let mut end_points = vec![];
+            let mut total = 0u32;
+            for _ in 0..contours {
+                total += n_points.u255_16()? as u32;
+                end_points.push(u16::try_from(total.checked_sub(1)?).ok()?);
+            }
+            // every point takes a flag byte
+            if total as usize > flags.data.len() - flags.offset {
+                return None;
+            }
+            let mut points = Vec::with_capacity(total as usize);
+            let (mut x, mut y) = (0i32, 0i32);
+            for _ in 0..total {
+                let flag = flags.u8()?;
+                let (dx, dy) = decode_triplet(flag & 0x7f, &mut glyphs)?;
+                x = x.checked_add(dx)?;
+                y = y.checked_add(dy)?;
+                points.push((x, y, flag & 0x80 == 0));
+            }
+            let instruction_length = glyphs.u255_16()?;
+            let instruction_data = instructions.read(instruction_length as usize)?;
+            if bbox.is_none() {
+                let (x_min, x_max) = (points.iter().map(|p| p.0).min()?, points.iter().map(|p| p.0).max()?);
+                let (y_min, y_max) = (points.iter().map(|p| p.1).min()?, points.iter().map(|p| p.1).max()?);
+                bbox = Some([x_min as i16, y_min as i16, x_max as i16, y_max as i16]);
+            }
+            let bbox = bbox?;
+            let overlap = overlap_bitmap.is_some_and(|bitmap| bit(bitmap, index));
+            write_i16s(&mut glyf, &[contours, bbox[0], bbox[1], bbox[2], bbox[3]]);
+            for end_point in end_points {
+                glyf.extend_from_slice(&end_point.to_be_bytes());
+            }
+            glyf.extend_from_slice(&instruction_length.to_be_bytes());
+            glyf.extend_from_slice(instruction_data);
+            write_points(&mut glyf, &points, overlap)?;
+            x_mins.push(bbox[0]);
+        }
+        // keeps the offsets even for the short loca format
+        while glyf.len() % 4 != 0 {
+            glyf.push(0);
+        }
+    }
+    write_offset(&mut loca, glyf.len(), index_format)?;
+    Some((glyf, loca, x_mins))
+}
+
+fn write_offset(loca: &mut Vec<u8>, offset: usize, index_format: u16) -> Option<()> {
+    if index_format == 0 {
+        loca.extend_from_slice(&u16::try_from(offset / 2).ok()?.to_be_bytes());
+    } else {
+        loca.extend_from_slice(&u32::try_from(offset).ok()?.to_be_bytes());
+    }
+    Some(())
+}
+
+fn write_i16s(data: &mut Vec<u8>, values: &[i16]) {
+    for value in values {
+        data.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+/// Writes the flags and coordinates of a simple glyph, using short vectors where they fit.
+fn write_points(glyf: &mut Vec<u8>, points: &[(i32, i32, bool)], overlap: bool) -> Option<()> {
+    let mut flags = vec![];
+    let mut xs = vec![];
+    let mut ys = vec![];
+    let (mut prev_x, mut prev_y) = (0, 0);
+    for (index, (x, y, on_curve)) in points.iter().enumerate() {
+        let mut flag = if *on_curve { ON_CURVE_POINT } else { 0 };
+        if index == 0 && overlap {
+            flag |= OVERLAP_SIMPLE;
+        }
+        let (dx, dy) = (x - prev_x, y - prev_y);
+        match dx {
+            0 => flag |= X_IS_SAME_OR_POSITIVE,
+            -255..=255 => {
+                flag |= X_SHORT_VECTOR | if dx > 0 { X_IS_SAME_OR_POSITIVE } else { 0 };
+                xs.push(dx.unsigned_abs() as u8);
+            }
+            _ => xs.extend_from_slice(&i16::try_from(dx).ok()?.to_be_bytes()),
+        }
+        match dy {
+            0 => flag |= Y_IS_SAME_OR_POSITIVE,
+            -255..=255 => {
+                flag |= Y_SHORT_VECTOR | if dy > 0 { Y_IS_SAME_OR_POSITIVE } else { 0 };
+                ys.push(dy.unsigned_abs() as u8);
+            }
+            _ => ys.extend_from_slice(&i16::try_from(dy).ok()?.to_be_bytes()),
+        }
+        flags.push(flag);
+        prev_x = *x;
+        prev_y = *y;
+    }
+    glyf.extend_from_slice(&flags);
+    glyf.extend_from_slice(&xs);
+    glyf.extend_from_slice(&ys);
+    Some(())
+}
+
+/// Decodes the point delta of a triplet flag, the sign of x and y is in the two low bits.
+fn decode_triplet(flag: u8, glyphs: &mut Reader) -> Option<(i32, i32)> {
+    let with_sign = |flag: u8, value: i32| if flag & 1 != 0 { value } else { -value };
+    let flag_i = flag as i32;
+    let delta = if flag < 10 {
+        (0, with_sign(flag, ((flag_i & 14) << 7) + glyphs.u8()? as i32))
+    } else if flag < 20 {
+        (with_sign(flag, (((flag_i - 10) & 14) << 7) + glyphs.u8()? as i32), 0)
+    } else if flag < 84 {
+        let b0 = flag_i - 20;
+        let b1 = glyphs.u8()? as i32;
+        (with_sign(flag, 1 + (b0 & 0x30) + (b1 >> 4)), with_sign(flag >> 1, 1 + ((b0 & 0x0c) << 2) + (b1 & 0x0f)))
+    } else if flag < 120 {
+        let b0 = flag_i - 84;
+        let (b1, b2) = (glyphs.u8()? as i32, glyphs.u8()? as i32);
+        (with_sign(flag, 1 + ((b0 / 12) << 8) + b1), with_sign(flag >> 1, 1 + (((b0 % 12) >> 2) << 8) + b2))
+    } else if flag < 124 {
+        let (b1, b2, b3) = (glyphs.u8()? as i32, glyphs.u8()? as i32, glyphs.u8()? as i32);
+        (with_sign(flag, (b1 << 4) + (b2 >> 4)), with_sign(flag >> 1, ((b2 & 0x0f) << 8) + b3))
+    } else {
+        let (b1, b2, b3, b4) = (glyphs.u8()? as i32, glyphs.u8()? as i32, glyphs.u8()? as i32, glyphs.u8()? as i32);
+        (with_sign(flag, (b1 << 8) + b2), with_sign(flag >> 1, (b3 << 8) + b4))
+    };
+    Some(delta)
+}
+
+/// Returns the length of the components of a composite glyph and whether instructions follow them.
+fn composite_length(data: &[u8]) -> Option<(usize, bool)> {
+    let mut rs = Reader::new(data);
+    let mut have_instructions = false;
+    loop {
+        let flags = rs.u16()?;
+        have_instructions |= flags & WE_HAVE_INSTRUCTIONS != 0;
+        // glyph index and arguments
+        let mut length = 2 + if flags & ARG_1_AND_2_ARE_WORDS != 0 { 4 } else { 2 };
+        if flags & WE_HAVE_A_SCALE != 0 {
+            length += 2;
+        } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+            length += 4;
+        } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+            length += 8;
+        }
+        rs.read(length)?;
+        if flags & MORE_COMPONENTS == 0 {
+            return Some((rs.offset, have_instructions));
+        }
+    }
+}
+
+/// Rebuilds the `hmtx` table, the left side bearings left out are the glyph `xMin`.
+fn reconstruct_hmtx(data: &[u8], num_glyphs: u16, num_h_metrics: u16, x_mins: &[i16]) -> Option<Vec<u8>> {
+    let mut rs = Reader::new(data);
+    let flags = rs.u8()?;
+    if flags & 3 == 0 || num_h_metrics == 0 || num_h_metrics > num_glyphs || x_mins.len() != num_glyphs as usize {
+        return None;
+    }
+    let advances = (0..num_h_metrics).map(|_| rs.u16()).collect::<Option<Vec<_>>>()?;
+    let mut bearings = vec![];
+    for (index, x_min) in x_mins.iter().enumerate() {
+        let stored = if index < num_h_metrics as usize { flags & 1 == 0 } else { flags & 2 == 0 };
+        bearings.push(if stored { rs.i16()? } else { *x_min });
+    }
+    let mut hmtx = vec![];
+    for (index, bearing) in bearings.iter().enumerate() {
+        if let Some(advance) = advances.get(index) {
+            hmtx.extend_from_slice(&advance.to_be_bytes());
+        }
+        hmtx.extend_from_slice(&bearing.to_be_bytes());
+    }
+    Some(hmtx)
+}
+
+/// Assembles an sfnt font with the tables sorted by tag and 4 bytes aligned.
+pub(crate) fn build_sfnt(flavor: u32, mut tables: Vec<([u8; 4], Vec<u8>)>) -> Vec<u8> {
+    tables.sort_by_key(|(tag, _)| *tag);
+    let num_tables = tables.len() as u16;
+    let mut entry_selector = 0u16;
+    while num_tables >> (entry_selector + 1) != 0 {
+        entry_selector += 1;
+    }
+    let search_range = (1u16 << entry_selector) * 16;
+
+    let mut font = vec![];
+    font.extend_from_slice(&flavor.to_be_bytes());
+    font.extend_from_slice(&num_tables.to_be_bytes());
+    font.extend_from_slice(&search_range.to_be_bytes());
+    font.extend_from_slice(&entry_selector.to_be_bytes());
+    font.extend_from_slice(&(num_tables * 16 - search_range).to_be_bytes());
+    let mut offset = 12 + 16 * tables.len();
+    let mut head_offset = None;
+    for (tag, data) in tables.iter_mut() {
+        if tag == b"head" && data.len() >= 12 {
+            // the whole font checksum is set once the font is assembled
+            data[8..12].copy_from_slice(&[0; 4]);
+            head_offset = Some(offset);
+        }
+        font.extend_from_slice(tag);
+        font.extend_from_slice(&checksum(data).to_be_bytes());
+        font.extend_from_slice(&(offset as u32).to_be_bytes());
+        font.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        offset += (data.len() + 3) & !3;
+    }
+    for (_, data) in tables.iter() {
+        font.extend_from_slice(data);
+        font.resize((font.len() + 3) & !3, 0);
+    }
+    if let Some(offset) = head_offset {
+        let adjustment = 0xb1b0_afbau32.wrapping_sub(checksum(&font));
+        font[offset + 8..offset + 12].copy_from_slice(&adjustment.to_be_bytes());
+    }
+    font
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    extern crate brotli;
+
+    use std::io::Write;
+    use crate::woff::decompress_woff;
+    use crate::woff2::{composite_length, decompress_woff2, KNOWN_TAGS, MAX_STREAM_LENGTH, Reader};
+    use crate::FontDB;
+    use painter_core::font::FontLoadError;
+
+    fn push_base128(data: &mut Vec<u8>, value: u32) {
+        let mut bytes = vec![(value & 0x7f) as u8];
+        let mut value = value >> 7;
+        while value != 0 {
+            bytes.insert(0, (value & 0x7f) as u8 | 0x80);
+            value >>= 7;
+        }
+        data.extend_from_slice(&bytes);
+    }
+
+    fn push_u255_16(data: &mut Vec<u8>, value: u16) {
+        data.push(253);
+        data.extend_from_slice(&value.to_be_bytes());
+    }
+
+    pub(crate) fn read_tables(font: &[u8]) -> Vec<([u8; 4], Vec<u8>)> {
+        let mut rs = Reader { offset: 4, data: font };
+        let num_tables = rs.u16().unwrap();
+        rs.offset = 12;
+        (0..num_tables).map(|_| {
+            let tag = rs.read(4).unwrap().try_into().unwrap();
+            let (_, offset, length) = (rs.u32().unwrap(), rs.u32().unwrap() as usize, rs.u32().unwrap() as usize);
+            (tag, font[offset..offset + length].to_vec())
+        }).collect()
+    }
+
+    /// Encodes the glyphs like the WOFF2 glyf transform, returns the table and the glyph `xMin`.
+    fn transform_glyf(glyf: &[u8], loca: &[u8], index_format: u16, num_glyphs: u16) -> (Vec<u8>, Vec<i16>) {
+        let mut streams = vec![vec![]; 7];
+        let mut x_mins = vec![];
+        let bitmap_len = ((num_glyphs as usize + 31) >> 5) << 2;
+        streams[5] = vec![0; bitmap_len];
+        let offset = |index: usize| if index_format == 0 {
+            u16::from_be_bytes([loca[index * 2], loca[index * 2 + 1]]) as usize * 2
+        } else {
+            u32::from_be_bytes(loca[index * 4..index * 4 + 4].try_into().unwrap()) as usize
+        };
+        for index in 0..num_glyphs as usize {
+            let data = &glyf[offset(index)..offset(index + 1)];
+            if data.is_empty() {
+                streams[0].extend_from_slice(&0i16.to_be_bytes());
+                x_mins.push(0);
+                continue;
+            }
+            let mut rs = Reader::new(data);
+            let contours = rs.i16().unwrap();
+            let bbox = [rs.i16().unwrap(), rs.i16().unwrap(), rs.i16().unwrap(), rs.i16().unwrap()];
+            x_mins.push(bbox[0]);
+            streams[0].extend_from_slice(&contours.to_be_bytes());
+            let mut explicit_bbox = true;
+            if contours < 0 {
+                let (length, have_instructions) = composite_length(&data[10..]).unwrap();
+                streams[4].extend_from_slice(rs.read(length).unwrap());
+                if have_instructions {
+                    let length = rs.u16().unwrap();
+                    push_u255_16(&mut streams[3], length);
+                    streams[6].extend_from_slice(rs.read(length as usize).unwrap());
+                }
+            } else {
+                let end_points = (0..contours).map(|_| rs.u16().unwrap() as i32).collect::<Vec<_>>();
+                let mut prev = -1;
+                for end_point in end_points.iter() {
+                    push_u255_16(&mut streams[1], (end_point - prev) as u16);
+                    prev = *end_point;
+                }
+                let instruction_length = rs.u16().unwrap();
+                let instructions = rs.read(instruction_length as usize).unwrap();
+                let total = (prev + 1) as usize;
+                let mut flags = vec![];
+                while flags.len() < total {
+                    let flag = rs.u8().unwrap();
+                    flags.push(flag);
+                    if flag & 0x08 != 0 {
+                        for _ in 0..rs.u8().unwrap() {
+                            flags.push(flag);
+                        }
+                    }
+                }
+                let mut coordinates = |short: u8, same: u8| flags.iter().map(|flag| match (flag & short != 0, flag & same != 0) {
+                    (true, positive) => if positive { rs.u8().unwrap() as i32 } else { -(rs.u8().unwrap() as i32) },
+                    (false, true) => 0,
+                    (false, false) => rs.i16().unwrap() as i32,
+                }).collect::<Vec<_>>();
+                let dxs = coordinates(0x02, 0x10);
+                let dys = coordinates(0x04, 0x20);
+                let (mut x, mut y) = (0, 0);
+                let mut computed = [i32::MAX, i32::MAX, i32::MIN, i32::MIN];
+                for ((flag, dx), dy) in flags.iter().zip(dxs).zip(dys) {
+                    let triplet = 124 + (dx >= 0) as u8 + (((dy >= 0) as u8) << 1);
+                    streams[2].push(triplet | if flag & 0x01 == 0 { 0x80 } else { 0 });
+                    streams[3].extend_from_slice(&(dx.unsigned_abs() as u16).to_be_bytes());
+                    streams[3].extend_from_slice(&(dy.unsigned_abs() as u16).to_be_bytes());
+                    x += dx;
+                    y += dy;
+                    computed = [computed[0].min(x), computed[1].min(y), computed[2].max(x), computed[3].max(y)];
+                }
+                push_u255_16(&mut streams[3], instruction_length);
+                streams[6].extend_from_slice(instructions);
+                explicit_bbox = computed.iter().zip(bbox).any(|(a, b)| *a != b as i32);
+            }
+            if explicit_bbox {
+                streams[5][index >> 3] |= 0x80 >> (index & 7);
+                for value in bbox {
+                    streams[5].extend_from_slice(&value.to_be_bytes());
+                }
+            }
+        }
+        let mut table = vec![0, 0, 0, 0];
+        table.extend_from_slice(&num_glyphs.to_be_bytes());
+        table.extend_from_slice(&index_format.to_be_bytes());
+        for stream in streams.iter() {
+            table.extend_from_slice(&(stream.len() as u32).to_be_bytes());
+        }
+        for stream in streams {
+            table.extend_from_slice(&stream);
+        }
+        (table, x_mins)
+    }
+
+    /// Encodes an sfnt font as WOFF2, with or without the glyf, loca and hmtx transforms.
+    fn encode_woff2(font: &[u8], transform: bool) -> Vec<u8> {
+        let tables = read_tables(font);
+        let table = |tag: &[u8; 4]| &tables.iter().find(|(t, _)| t == tag).unwrap().1;
+        let index_format = Reader { offset: 50, data: table(b"head") }.u16().unwrap();
+        let num_glyphs = Reader { offset: 4, data: table(b"maxp") }.u16().unwrap();
+        let num_h_metrics = Reader { offset: 34, data: table(b"hhea") }.u16().unwrap();
+        let (glyf, x_mins) = transform_glyf(table(b"glyf"), table(b"loca"), index_format, num_glyphs);
+
+        let hmtx = table(b"hmtx");
+        let mut bearings = (0..num_glyphs as usize).map(|index| {
+            let offset = if index < num_h_metrics as usize { index * 4 + 2 } else { num_h_metrics as usize * 2 + index * 2 };
+            Reader { offset, data: hmtx }.i16().unwrap()
+        });
+        assert!(bearings.by_ref().zip(x_mins.iter()).all(|(bearing, x_min)| bearing == *x_min));
+        let mut transformed_hmtx = vec![0x03];
+        for index in 0..num_h_metrics as usize {
+            transformed_hmtx.extend_from_slice(&hmtx[index * 4..index * 4 + 2]);
+        }
+
+        let mut directory = vec![];
+        let mut stream = vec![];
+        for (tag, data) in tables.iter() {
+            let (version, transformed) = match (tag, transform) {
+                (b"glyf", true) => (0, Some(glyf.as_slice())),
+                (b"loca", true) => (0, Some(&[][..])),
+                (b"hmtx", true) => (1, Some(transformed_hmtx.as_slice())),
+                (b"glyf" | b"loca", false) => (3, None),
+                _ => (0, None),
+            };
+            match KNOWN_TAGS.iter().position(|known| *known == tag) {
+                Some(index) => directory.push(index as u8 | version << 6),
+                None => {
+                    directory.push(63 | version << 6);
+                    directory.extend_from_slice(tag);
+                }
+            }
+            push_base128(&mut directory, data.len() as u32);
+            if let Some(transformed) = transformed {
+                push_base128(&mut directory, transformed.len() as u32);
+            }
+            stream.extend_from_slice(transformed.unwrap_or(data));
+        }
+        let mut compressed = vec![];
+        {
+            let mut writer = brotli::CompressorWriter::new(&mut compressed, 4096, 5, 22);
+            writer.write_all(&stream).unwrap();
+        }
+
+        let mut woff2 = b"wOF2".to_vec();
+        woff2.extend_from_slice(&font[0..4]);
+        woff2.extend_from_slice(&((48 + directory.len() + compressed.len()) as u32).to_be_bytes());
+        woff2.extend_from_slice(&(tables.len() as u16).to_be_bytes());
+        woff2.extend_from_slice(&[0, 0]);
+        woff2.extend_from_slice(&(font.len() as u32).to_be_bytes());
+        woff2.extend_from_slice(&(compressed.len() as u32).to_be_bytes());
+        woff2.extend_from_slice(&[0, 1, 0, 0]);
+        woff2.extend_from_slice(&[0; 20]);
+        woff2.extend_from_slice(&directory);
+        woff2.extend_from_slice(&compressed);
+        woff2
+    }
+
+    #[derive(Default)]
+    struct Outline(String);
+
+    impl ttf_parser::OutlineBuilder for Outline {
+        fn move_to(&mut self, x: f32, y: f32) {
+            self.0 += &format!("M{} {}", x, y);
+        }
+        fn line_to(&mut self, x: f32, y: f32) {
+            self.0 += &format!("L{} {}", x, y);
+        }
+        fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+            self.0 += &format!("Q{} {} {} {}", x1, y1, x, y);
+        }
+        fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+            self.0 += &format!("C{} {} {} {} {} {}", x1, y1, x2, y2, x, y);
+        }
+        fn close(&mut self) {
+            self.0 += "Z";
+        }
+    }
+
+    #[test]
+    fn decompress() {
+        let font = decompress_woff(include_bytes!("../test/zc2016.woff")).unwrap();
+        let original = ttf_parser::Face::parse(&font, 0).unwrap();
+        for transform in [false, true] {
+            let woff2 = encode_woff2(&font, transform);
+            let result = decompress_woff2(&woff2).unwrap();
+            let face = ttf_parser::Face::parse(&result, 0).unwrap();
+            assert_eq!(face.number_of_glyphs(), original.number_of_glyphs());
+            for id in 0..original.number_of_glyphs() {
+                let id = ttf_parser::GlyphId(id);
+                let (mut expected, mut actual) = (Outline::default(), Outline::default());
+                assert_eq!(original.outline_glyph(id, &mut expected), face.outline_glyph(id, &mut actual));
+                assert_eq!(expected.0, actual.0);
+                assert_eq!(original.glyph_hor_advance(id), face.glyph_hor_advance(id));
+                assert_eq!(original.glyph_hor_side_bearing(id), face.glyph_hor_side_bearing(id));
+            }
+            assert_eq!(original.glyph_index('字'), face.glyph_index('字'));
+
+            let mut db = FontDB::new();
+            assert!(db.load_font(&woff2).is_ok());
+            assert_eq!(db.load_font(&woff2[..woff2.len() - 8]), Err(FontLoadError::SizeMismatch));
+        }
+    }
+
+    #[test]
+    fn oversized_stream() {
+        let header = |directory: &[u8], compressed: &[u8]| {
+            let mut woff2 = b"wOF2".to_vec();
+            woff2.extend_from_slice(&[0, 1, 0, 0]);
+            woff2.extend_from_slice(&((48 + directory.len() + compressed.len()) as u32).to_be_bytes());
+            woff2.extend_from_slice(&[0, 1, 0, 0]);
+            woff2.extend_from_slice(&[0; 4]);
+            woff2.extend_from_slice(&(compressed.len() as u32).to_be_bytes());
+            woff2.extend_from_slice(&[0; 24]);
+            woff2.extend_from_slice(directory);
+            woff2.extend_from_slice(compressed);
+            woff2
+        };
+        // a cmap table declared beyond the stream limit is rejected before the invalid stream is inflated
+        let mut directory = vec![0];
+        push_base128(&mut directory, MAX_STREAM_LENGTH as u32 + 1);
+        assert_eq!(decompress_woff2(&header(&directory, &[0xff; 4])), Err(FontLoadError::SizeMismatch));
+        // a transformed loca with data
+        let mut directory = vec![11];
+        push_base128(&mut directory, 8);
+        push_base128(&mut directory, 8);
+        let mut compressed = vec![];
+        brotli::CompressorWriter::new(&mut compressed, 4096, 5, 22).write_all(&[0; 8]).unwrap();
+        assert_eq!(decompress_woff2(&header(&directory, &compressed)), Err(FontLoadError::SizeMismatch));
+    }
+}
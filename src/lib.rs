@@ -86,7 +86,7 @@ mod test {
     fn render() {
         let file = include_bytes!("../font/out.ttf") as &[u8];
         let mut font_db = FontDB::new();
-        font_db.load_font(file);
+        font_db.load_font(file).unwrap();
         let ctx = draw(&mut font_db);
         let mut renderer = SkiaCPURender::new(2000, 2000);
         renderer.force_lq();
@@ -98,7 +98,7 @@ mod test {
     fn render_svg() {
         let file = include_bytes!("../font/test/zc2016.woff") as &[u8];
         let mut font_db = FontDB::new();
-        font_db.load_font(file);
+        font_db.load_font(file).unwrap();
         let ctx = draw(&mut font_db);
         let renderer = SvgRender::new(2000.0, 2000.0);
         let data = ctx.render(Box::new(renderer));
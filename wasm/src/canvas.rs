@@ -2,10 +2,12 @@ use crate::web_sys::{HtmlCanvasElement, HtmlElement, HtmlImageElement, ImageData
 use crate::wasm_bindgen::prelude::{wasm_bindgen};
 use crate::wasm_bindgen::{JsValue, JsCast, Clamped};
 use crate::base64;
+use crate::js_sys::{Array, Object, Reflect};
 
 use crate::painter_core::backend::skia_cpu::{SkiaCPURender, ImageDataRender};
 use crate::painter_core::backend::svg::SvgRender;
 use crate::painter_core::context2d::Context;
+use crate::painter_core::font::{FaceId, FontStyle};
 use crate::painter_font::FontDB;
 
 use crate::context::Context2d;
@@ -114,10 +116,56 @@ impl Canvas {
         Some(0)
     }
 
+    /// Returns the ids of the loaded faces, one per face of a collection.
     #[wasm_bindgen(js_name = loadFont)]
-    pub fn load_font(&mut self, buf: Vec<u8>) -> Option<usize> {
-        self.font_db.load_font(buf.as_slice())?;
-        Some(0)
+    pub fn load_font(&mut self, buf: Vec<u8>) -> Result<Vec<u32>, JsValue> {
+        let ids = self.font_db.load_font(buf.as_slice())
+            .map_err(|err| JsValue::from_str(&format!("loadFont: {}", err)))?;
+        Ok(ids.into_iter().map(|id| id.0).collect())
+    }
+
+    /// Lists the loaded faces as `{ id, family, postScriptName, weight, style, stretch, monospaced,
+    /// glyphCount, unicodeRanges }` objects, the ranges are inclusive `[start, end]` code point pairs.
+    pub fn faces(&self) -> Array {
+        self.font_db.faces().into_iter().map(|face| {
+            let style = match face.style {
+                FontStyle::Normal => "normal",
+                FontStyle::Italic => "italic",
+                FontStyle::Oblique => "oblique",
+            };
+            let ranges: Array = face.unicode_ranges.iter()
+                .map(|(start, end)| Array::of2(&JsValue::from(*start), &JsValue::from(*end)))
+                .collect();
+            let object = Object::new();
+            let fields = [
+                ("id", JsValue::from(face.id.0)),
+                ("family", JsValue::from(face.family)),
+                ("postScriptName", JsValue::from(face.post_script_name)),
+                ("weight", JsValue::from(face.weight)),
+                ("style", JsValue::from(style)),
+                ("stretch", JsValue::from(face.stretch)),
+                ("monospaced", JsValue::from(face.monospaced)),
+                ("glyphCount", JsValue::from(face.glyph_count)),
+                ("unicodeRanges", ranges.into()),
+            ];
+            for (key, value) in fields {
+                Reflect::set(&object, &JsValue::from(key), &value).ok();
+            }
+            JsValue::from(object)
+        }).collect()
+    }
+
+    /// Sets the loaded family a css generic family like `serif` or `monospace` resolves to,
+    /// `false` for an unknown generic family.
+    #[wasm_bindgen(js_name = setGenericFamily)]
+    pub fn set_generic_family(&mut self, generic: &str, family: &str) -> bool {
+        self.font_db.set_generic_family(generic, family)
+    }
+
+    /// Unloads a face by the id `loadFont` returned, `false` when it is not loaded.
+    #[wasm_bindgen(js_name = removeFace)]
+    pub fn remove_face(&mut self, id: u32) -> bool {
+        self.font_db.remove_face(FaceId(id))
     }
 
     pub fn render(&self) -> Option<usize> {
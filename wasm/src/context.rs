@@ -1,18 +1,25 @@
 use std::ops::{Deref, DerefMut};
 use std::str::FromStr;
 use svgtypes::Color;
+use crate::web_sys::ImageData;
 
-use crate::wasm_bindgen::JsValue;
+use crate::wasm_bindgen::{Clamped, JsValue};
 use crate::wasm_bindgen::prelude::*;
 
 use crate::painter_core::context2d::Context;
-use crate::painter_core::{LineCap, LineJoin};
+use crate::painter_core::{Image, LineCap, LineJoin};
+use crate::painter_core::image::ImageData as CoreImageData;
 use crate::painter_core::paint::{FillRule, Paint};
 use crate::painter_core::transform::Transform;
-use crate::painter_core::font::FontBucket;
+use crate::painter_core::f32x2;
+use crate::painter_core::font::{FaceSelector, FontBucket, FontFeature, FontKerning, FontVariation};
+use crate::painter_core::style_bucket::{TextAlign, TextBaseLine, TextDirection};
 
-use crate::path::array2path;
+use crate::path::{array2path, get_radii};
 use crate::canvas::Canvas;
+use crate::gradient::CanvasGradient;
+use crate::pattern::{CanvasPattern, get_repetition};
+use crate::text_metrics::TextMetrics;
 
 #[wasm_bindgen]
 pub struct Context2d {
@@ -54,6 +61,16 @@ impl Context2d {
         self.inner
     }
 
+    #[wasm_bindgen(setter = font)]
+    pub fn set_font(&mut self, font: &str) {
+        self.inner.ctx.set_font(font);
+    }
+
+    #[wasm_bindgen(getter = font)]
+    pub fn get_font(&self) -> String {
+        self.inner.ctx.get_font()
+    }
+
     #[wasm_bindgen(setter = fontFamily)]
     pub fn set_font_family(&mut self, family: &str) {
         self.inner.ctx.set_font_family(family)
@@ -78,6 +95,26 @@ impl Context2d {
         self.inner.ctx.set_fill_style(p);
     }
 
+    #[wasm_bindgen(js_name = setStrokeGradient)]
+    pub fn set_stroke_gradient(&mut self, gradient: &CanvasGradient) {
+        self.inner.ctx.set_stroke_style(Paint::from_shader(gradient.0.clone()));
+    }
+
+    #[wasm_bindgen(js_name = setFillGradient)]
+    pub fn set_fill_gradient(&mut self, gradient: &CanvasGradient) {
+        self.inner.ctx.set_fill_style(Paint::from_shader(gradient.0.clone()));
+    }
+
+    #[wasm_bindgen(js_name = setStrokePattern)]
+    pub fn set_stroke_pattern(&mut self, pattern: &CanvasPattern) {
+        self.inner.ctx.set_stroke_style(Paint::from_shader(pattern.0.clone()));
+    }
+
+    #[wasm_bindgen(js_name = setFillPattern)]
+    pub fn set_fill_pattern(&mut self, pattern: &CanvasPattern) {
+        self.inner.ctx.set_fill_style(Paint::from_shader(pattern.0.clone()));
+    }
+
     #[wasm_bindgen(setter = lineWidth)]
     pub fn set_line_width(&mut self, width: f32) {
         self.inner.ctx.set_line_width(width);
@@ -105,6 +142,91 @@ impl Context2d {
         self.inner.ctx.set_line_join(line_join);
     }
 
+    #[wasm_bindgen(setter = fontKerning)]
+    pub fn set_font_kerning(&mut self, font_kerning: &str) {
+        let font_kerning = match font_kerning {
+            "normal" => { FontKerning::Normal }
+            "none" => { FontKerning::None }
+            _ => { FontKerning::Auto }
+        };
+        self.inner.ctx.set_font_kerning(font_kerning);
+    }
+
+    /// Not part of the canvas api, takes a css `font-feature-settings` value like `"liga" 0, "ss01"`.
+    #[wasm_bindgen(setter = fontFeatureSettings)]
+    pub fn set_font_feature_settings(&mut self, settings: &str) {
+        if let Some(features) = FontFeature::parse_list(settings) {
+            self.inner.ctx.set_font_feature_settings(features);
+        }
+    }
+
+    /// Not part of the canvas api, takes a css `font-variation-settings` value like `"wght" 650, "wdth" 80`.
+    #[wasm_bindgen(setter = fontVariationSettings)]
+    pub fn set_font_variation_settings(&mut self, settings: &str) {
+        if let Some(variations) = FontVariation::parse_list(settings) {
+            self.inner.ctx.set_font_variation_settings(variations);
+        }
+    }
+
+    /// Not part of the canvas api, takes a face index in the matched font collection or a PostScript name,
+    /// `auto` goes back to the family match.
+    #[wasm_bindgen(setter = fontFace)]
+    pub fn set_font_face(&mut self, face: &str) {
+        let face = match face {
+            "auto" | "" => FaceSelector::Family,
+            face => match face.parse() {
+                Ok(index) => FaceSelector::Index(index),
+                Err(_) => FaceSelector::PostScriptName(face.to_string()),
+            },
+        };
+        self.inner.ctx.set_font_face(face);
+    }
+
+    #[wasm_bindgen(setter = direction)]
+    pub fn set_direction(&mut self, direction: &str) {
+        let direction = match direction {
+            "ltr" => { TextDirection::Ltr }
+            "rtl" => { TextDirection::Rtl }
+            _ => { TextDirection::Inherit }
+        };
+        self.inner.ctx.set_direction(direction);
+    }
+
+    #[wasm_bindgen(setter = textAlign)]
+    pub fn set_text_align(&mut self, text_align: &str) {
+        let text_align = match text_align {
+            "left" => { TextAlign::Left }
+            "right" => { TextAlign::Right }
+            "center" => { TextAlign::Center }
+            "end" => { TextAlign::End }
+            _ => { TextAlign::Start }
+        };
+        self.inner.ctx.set_text_align(text_align);
+    }
+
+    #[wasm_bindgen(setter = textBaseline)]
+    pub fn set_text_base_line(&mut self, text_base_line: &str) {
+        let text_base_line = match text_base_line {
+            "top" => { TextBaseLine::Top }
+            "hanging" => { TextBaseLine::Hanging }
+            "middle" => { TextBaseLine::Middle }
+            "ideographic" => { TextBaseLine::Ideographic }
+            "bottom" => { TextBaseLine::Bottom }
+            _ => { TextBaseLine::Alphabetic }
+        };
+        self.inner.ctx.set_text_base_line(text_base_line);
+    }
+
+    #[wasm_bindgen(setter = globalAlpha)]
+    pub fn set_global_alpha(&mut self, global_alpha: f32) {
+        self.inner.ctx.set_global_alpha(global_alpha)
+    }
+
+    #[wasm_bindgen(setter = imageSmoothingEnabled)]
+    pub fn set_image_smoothing_enabled(&mut self, image_smoothing_enabled: bool) {
+        self.inner.ctx.set_image_smoothing_enabled(image_smoothing_enabled)
+    }
+
     #[wasm_bindgen(setter = miterLimit)]
     pub fn set_miter_limit(&mut self, miter_limit: f32) {
         self.inner.ctx.set_miter_limit(miter_limit)
@@ -119,8 +241,9 @@ impl Context2d {
     }
 
     #[wasm_bindgen(js_name = arcTo)]
-    pub fn arc_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) {
-        self.inner.ctx.arc_to(x1, y1, x2, y2);
+    pub fn arc_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, radius: f32) -> Result<(), JsValue> {
+        self.inner.ctx.arc_to(x1, y1, x2, y2, radius)
+            .ok_or_else(|| JsValue::from_str("IndexSizeError: the radius provided is negative"))
     }
 
     #[wasm_bindgen(js_name = beginPath)]
@@ -148,36 +271,50 @@ impl Context2d {
     }
 
     #[wasm_bindgen(js_name = createConicGradient)]
-    pub fn create_conic_gradient() {
-        todo!()
+    pub fn create_conic_gradient(&self, start_angle: f32, x: f32, y: f32) -> CanvasGradient {
+        CanvasGradient(self.inner.ctx.create_conic_gradient(start_angle, x, y).into())
     }
 
     #[wasm_bindgen(js_name = createImageData)]
-    pub fn create_image_data() {
-        todo!()
+    pub fn create_image_data(&self, width: u32, height: u32) -> Option<ImageData> {
+        to_js_image_data(&self.inner.ctx.create_image_data(width, height)?)
     }
 
     #[wasm_bindgen(js_name = createLinearGradient)]
-    pub fn create_linear_gradient() {
-        todo!()
+    pub fn create_linear_gradient(&self, x0: f32, y0: f32, x1: f32, y1: f32) -> CanvasGradient {
+        CanvasGradient(self.inner.ctx.create_linear_gradient(x0, y0, x1, y1).into())
     }
 
     #[wasm_bindgen(js_name = createPattern)]
-    pub fn create_pattern() {
-        todo!()
+    pub fn create_pattern(&self, data: Vec<u8>, width: u32, height: u32, repetition: JsValue) -> Option<CanvasPattern> {
+        let image = Image::from_rgba(&data, f32x2([width as f32, height as f32]))?;
+        let pattern = self.inner.ctx.create_pattern(&image, get_repetition(repetition)?)?;
+        Some(CanvasPattern(pattern))
     }
 
     #[wasm_bindgen(js_name = createRadialGradient)]
-    pub fn create_radial_gradient() {
-        todo!()
+    pub fn create_radial_gradient(&self, x0: f32, y0: f32, r0: f32, x1: f32, y1: f32, r1: f32) -> Option<CanvasGradient> {
+        let gradient = self.inner.ctx.create_radial_gradient(x0, y0, r0, x1, y1, r1)?;
+        Some(CanvasGradient(gradient.into()))
     }
 
+    /// Draws an encoded PNG, JPEG, GIF or WebP file.
     #[wasm_bindgen(js_name = drawImage)]
-    pub fn draw_image(&mut self) {
-        todo!()
+    pub fn draw_image(&mut self, data: &[u8], dx: f32, dy: f32) {
+        Image::decode(data).and_then(|image| self.inner.ctx.draw_image(&image, dx, dy));
     }
-    pub fn ellipse(&mut self) {
-        todo!()
+
+    #[wasm_bindgen(js_name = drawImageWithSize)]
+    pub fn draw_image_with_size(&mut self, data: &[u8], dx: f32, dy: f32, dw: f32, dh: f32) {
+        Image::decode(data).and_then(|image| self.inner.ctx.draw_image_with_size(&image, dx, dy, dw, dh));
+    }
+
+    #[wasm_bindgen(js_name = drawImageWithSrcRect)]
+    pub fn draw_image_with_src_rect(&mut self, data: &[u8], sx: f32, sy: f32, sw: f32, sh: f32, dx: f32, dy: f32, dw: f32, dh: f32) {
+        Image::decode(data).and_then(|image| self.inner.ctx.draw_image_with_src_rect(&image, sx, sy, sw, sh, dx, dy, dw, dh));
+    }
+    pub fn ellipse(&mut self, x: f32, y: f32, radius_x: f32, radius_y: f32, rotation: f32, start_angle: f32, end_angle: f32, anticlockwise: Option<bool>) {
+        self.inner.ctx.ellipse(x, y, radius_x, radius_y, rotation, start_angle, end_angle, anticlockwise.unwrap_or(false));
     }
     pub fn fill(&mut self, path: Option<Box<[f32]>>, fill_rule: JsValue) {
         self.inner.ctx.fill(path.and_then(|p| array2path(p.to_vec())), get_fill_rule(fill_rule));
@@ -196,8 +333,9 @@ impl Context2d {
     pub fn get_context_attributes(&self) {
         todo!()
     }
-    pub fn get_image_data(&self) {
-        todo!()
+    #[wasm_bindgen(js_name = getImageData)]
+    pub fn get_image_data(&self, sx: i32, sy: i32, sw: i32, sh: i32) -> Option<ImageData> {
+        to_js_image_data(&self.inner.ctx.get_image_data(sx, sy, sw, sh)?)
     }
 
     #[wasm_bindgen(getter = lineDash)]
@@ -215,8 +353,9 @@ impl Context2d {
     }
 
     #[wasm_bindgen(js_name = measureText)]
-    pub fn measure_text() {
-        todo!()
+    pub fn measure_text(&mut self, text: &str) -> Option<TextMetrics> {
+        let fb: &mut dyn FontBucket = &mut self.inner.font_db;
+        self.inner.ctx.measure_text(text, Some(fb)).map(TextMetrics)
     }
 
     #[wasm_bindgen(js_name = moveTo)]
@@ -225,8 +364,15 @@ impl Context2d {
     }
 
     #[wasm_bindgen(js_name = putImageData)]
-    pub fn put_image_data(&mut self) {
-        todo!()
+    pub fn put_image_data(&mut self, image_data: &ImageData, dx: i32, dy: i32, dirty_x: Option<i32>, dirty_y: Option<i32>, dirty_width: Option<i32>, dirty_height: Option<i32>) {
+        let (width, height) = (image_data.width(), image_data.height());
+        if let Some(image_data) = CoreImageData::from_data(image_data.data().0, width, height) {
+            self.inner.ctx.put_image_data_with_dirty_rect(
+                &image_data, dx, dy,
+                dirty_x.unwrap_or(0), dirty_y.unwrap_or(0),
+                dirty_width.unwrap_or(width as i32), dirty_height.unwrap_or(height as i32),
+            );
+        }
     }
 
     #[wasm_bindgen(js_name = quadraticCurveTo)]
@@ -237,6 +383,13 @@ impl Context2d {
         self.inner.ctx.rect(x, y, width, height);
     }
 
+    #[wasm_bindgen(js_name = roundRect)]
+    pub fn round_rect(&mut self, x: f32, y: f32, width: f32, height: f32, radii: JsValue) {
+        if let Some(radii) = get_radii(radii) {
+            self.inner.ctx.round_rect(x, y, width, height, &radii);
+        }
+    }
+
     #[wasm_bindgen(js_name = resetTransform)]
     pub fn reset_transform(&mut self) {
         self.inner.ctx.reset_transform();
@@ -275,4 +428,8 @@ impl Context2d {
     pub fn translate(&mut self, x: f32, y: f32) {
         self.inner.ctx.translate(x, y)
     }
-}
\ No newline at end of file
+}
+
+fn to_js_image_data(image_data: &CoreImageData) -> Option<ImageData> {
+    ImageData::new_with_u8_clamped_array_and_sh(Clamped(&image_data.data), image_data.width, image_data.height).ok()
+}
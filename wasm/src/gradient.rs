@@ -0,0 +1,26 @@
+use std::str::FromStr;
+
+use crate::wasm_bindgen::prelude::*;
+
+use crate::painter_core::paint::color::Color;
+use crate::painter_core::paint::shader::Shader;
+
+#[wasm_bindgen]
+pub struct CanvasGradient(pub(crate) Shader);
+
+#[wasm_bindgen]
+impl CanvasGradient {
+    #[wasm_bindgen(js_name = addColorStop)]
+    pub fn add_color_stop(&mut self, offset: f32, color: &str) -> Result<(), JsValue> {
+        let color = svgtypes::Color::from_str(color)
+            .map_err(|_| JsValue::from_str("SyntaxError: the color provided could not be parsed"))?;
+        let color = Color::from_rgba8(color.red, color.green, color.blue, color.alpha);
+        let added = match &mut self.0 {
+            Shader::LinearGradient(gradient) => gradient.add_color_stop(offset, color),
+            Shader::RadialGradient(gradient) => gradient.add_color_stop(offset, color),
+            Shader::ConicGradient(gradient) => gradient.add_color_stop(offset, color),
+            _ => Some(())
+        };
+        added.ok_or_else(|| JsValue::from_str("IndexSizeError: the offset provided is outside the range [0, 1]"))
+    }
+}
@@ -1,6 +1,9 @@
 mod canvas;
 mod context;
+mod gradient;
 mod path;
+mod pattern;
+mod text_metrics;
 
 extern crate painter_core;
 extern crate painter_font;
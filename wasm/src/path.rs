@@ -2,7 +2,8 @@ use std::ops::Add;
 use wasm_bindgen::JsValue;
 use svgtypes::{PathParser, PathSegment};
 use crate::js_console_info;
-use crate::painter_core::path::PathData as PD;
+use crate::painter_core::path::{CornerRadius, PathData as PD};
+use crate::js_sys::{Array, Reflect};
 use crate::wasm_bindgen::prelude::wasm_bindgen;
 
 #[wasm_bindgen]
@@ -26,6 +27,17 @@ impl Path2D {
         self.0.arc2(x, y, radius, start_angle, end_angle, anticlockwise)
     }
 
+    pub fn ellipse(&mut self, x: f32, y: f32, radius_x: f32, radius_y: f32, rotation: f32, start_angle: f32, end_angle: f32, anticlockwise: Option<bool>) {
+        self.0.ellipse(x, y, radius_x, radius_y, rotation, start_angle, end_angle, anticlockwise.unwrap_or(false));
+    }
+
+    #[wasm_bindgen(js_name = roundRect)]
+    pub fn round_rect(&mut self, x: f32, y: f32, width: f32, height: f32, radii: JsValue) {
+        if let Some(mut path) = get_radii(radii).and_then(|radii| PD::create_round_rect(x, y, width, height, &radii)) {
+            self.0.append(&mut path);
+        }
+    }
+
     #[wasm_bindgen(js_name = moveTo)]
     pub fn move_to(&mut self, x: f32, y: f32) {
         self.0.move_to(x, y);
@@ -128,6 +140,25 @@ impl PathSegmentBox {
 }
 
 
+/// Reads `roundRect` radii: a number, or a list of numbers and `{ x, y }` objects.
+pub(crate) fn get_radii(value: JsValue) -> Option<Vec<CornerRadius>> {
+    let get_radius = |value: JsValue| -> Option<CornerRadius> {
+        if let Some(radius) = value.as_f64() {
+            return Some(CornerRadius::from(radius as f32));
+        }
+        let x = Reflect::get(&value, &JsValue::from_str("x")).ok()?.as_f64().unwrap_or(0.0);
+        let y = Reflect::get(&value, &JsValue::from_str("y")).ok()?.as_f64().unwrap_or(0.0);
+        Some(CornerRadius::from((x as f32, y as f32)))
+    };
+    if value.is_undefined() {
+        Some(vec![CornerRadius::default()])
+    } else if Array::is_array(&value) {
+        Array::from(&value).iter().map(get_radius).collect()
+    } else {
+        Some(vec![get_radius(value)?])
+    }
+}
+
 pub(crate) fn array2path(input: Vec<f32>) -> Option<PD> {
     let len = input.get(0)?.clone() as usize;
     let mut count = 0usize;
@@ -0,0 +1,27 @@
+use crate::wasm_bindgen::JsValue;
+use crate::wasm_bindgen::prelude::*;
+
+use crate::painter_core::paint::shader::{Pattern, Repetition};
+use crate::painter_core::transform::Transform;
+
+#[wasm_bindgen]
+pub struct CanvasPattern(pub(crate) Pattern);
+
+#[wasm_bindgen]
+impl CanvasPattern {
+    #[wasm_bindgen(js_name = setTransform)]
+    pub fn set_transform(&mut self, a: f32, b: f32, c: f32, d: f32, e: f32, f: f32) {
+        self.0.set_transform(&Transform::new(a, b, c, d, e, f))
+    }
+}
+
+pub(crate) fn get_repetition(value: JsValue) -> Option<Repetition> {
+    let value = value.as_string().unwrap_or(format!(""));
+    match value.as_str() {
+        "" | "repeat" => Some(Repetition::Repeat),
+        "repeat-x" => Some(Repetition::RepeatX),
+        "repeat-y" => Some(Repetition::RepeatY),
+        "no-repeat" => Some(Repetition::NoRepeat),
+        _ => None
+    }
+}
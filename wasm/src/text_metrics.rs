@@ -0,0 +1,69 @@
+use crate::wasm_bindgen::prelude::*;
+
+use crate::painter_core::font::TextMetrics as Metrics;
+
+#[wasm_bindgen]
+pub struct TextMetrics(pub(crate) Metrics);
+
+#[wasm_bindgen]
+impl TextMetrics {
+    #[wasm_bindgen(getter)]
+    pub fn width(&self) -> f32 {
+        self.0.width
+    }
+
+    #[wasm_bindgen(getter = actualBoundingBoxLeft)]
+    pub fn actual_bounding_box_left(&self) -> f32 {
+        self.0.actual_bounding_box_left
+    }
+
+    #[wasm_bindgen(getter = actualBoundingBoxRight)]
+    pub fn actual_bounding_box_right(&self) -> f32 {
+        self.0.actual_bounding_box_right
+    }
+
+    #[wasm_bindgen(getter = actualBoundingBoxAscent)]
+    pub fn actual_bounding_box_ascent(&self) -> f32 {
+        self.0.actual_bounding_box_ascent
+    }
+
+    #[wasm_bindgen(getter = actualBoundingBoxDescent)]
+    pub fn actual_bounding_box_descent(&self) -> f32 {
+        self.0.actual_bounding_box_descent
+    }
+
+    #[wasm_bindgen(getter = fontBoundingBoxAscent)]
+    pub fn font_bounding_box_ascent(&self) -> f32 {
+        self.0.font_bounding_box_ascent
+    }
+
+    #[wasm_bindgen(getter = fontBoundingBoxDescent)]
+    pub fn font_bounding_box_descent(&self) -> f32 {
+        self.0.font_bounding_box_descent
+    }
+
+    #[wasm_bindgen(getter = emHeightAscent)]
+    pub fn em_height_ascent(&self) -> f32 {
+        self.0.em_height_ascent
+    }
+
+    #[wasm_bindgen(getter = emHeightDescent)]
+    pub fn em_height_descent(&self) -> f32 {
+        self.0.em_height_descent
+    }
+
+    #[wasm_bindgen(getter = hangingBaseline)]
+    pub fn hanging_baseline(&self) -> f32 {
+        self.0.hanging_baseline
+    }
+
+    #[wasm_bindgen(getter = alphabeticBaseline)]
+    pub fn alphabetic_baseline(&self) -> f32 {
+        self.0.alphabetic_baseline
+    }
+
+    #[wasm_bindgen(getter = ideographicBaseline)]
+    pub fn ideographic_baseline(&self) -> f32 {
+        self.0.ideographic_baseline
+    }
+}